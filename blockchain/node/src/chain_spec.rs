@@ -1,6 +1,7 @@
 use fractal_protocol_blockchain_runtime::{
-    AccountId, AuraConfig, BalancesConfig, FractalMintingConfig, GenesisConfig, GrandpaConfig,
-    Signature, SudoConfig, SystemConfig, WASM_BINARY,
+    AccountId, AuraConfig, BalancesConfig, BlockNumber, Destination, FractalMintingConfig,
+    FractalStakingConfig, FractalTokenDistributionConfig, GenesisConfig, GrandpaConfig, MintQuota,
+    Purpose, Signature, SudoConfig, SystemConfig, DAYS, HOURS, MINUTES, WASM_BINARY,
 };
 use sc_service::{ChainType, Properties};
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -46,6 +47,30 @@ pub fn fractal_properties() -> Properties {
     p
 }
 
+/// Lock tiers as `(blocks, shares per coin)`, and the minimum stake.
+fn fractal_staking_config(
+    lock_period_shares: Vec<(BlockNumber, u32)>,
+    minimum_stake: Balance,
+) -> FractalStakingConfig {
+    FractalStakingConfig {
+        lock_period_shares,
+        minimum_stake: Some(minimum_stake),
+    }
+}
+
+/// Issuance is split evenly between data capture rewards and staking rewards.
+fn fractal_token_distribution_config(
+    allowed_to_mint: Vec<(AccountId, MintQuota<Balance, BlockNumber>)>,
+) -> FractalTokenDistributionConfig {
+    FractalTokenDistributionConfig {
+        destination_weights: vec![
+            (Destination::Purpose(Purpose::DataCaptureRewards), 1),
+            (Destination::Purpose(Purpose::StakingRewards), 1),
+        ],
+        allowed_to_mint,
+    }
+}
+
 pub fn development_config() -> Result<ChainSpec, String> {
     let wasm_binary = WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?;
     Ok(ChainSpec::from_genesis(
@@ -72,6 +97,7 @@ pub fn development_config() -> Result<ChainSpec, String> {
                     fractal_authoritative_account: get_account_id_from_seed::<sr25519::Public>(
                         "Ferdie",
                     ),
+                    fractal_public_keys: vec![get_from_seed::<sr25519::Public>("Ferdie")],
                 },
             )
         },
@@ -126,6 +152,7 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
                     fractal_authoritative_account: get_account_id_from_seed::<sr25519::Public>(
                         "Ferdie",
                     ),
+                    fractal_public_keys: vec![get_from_seed::<sr25519::Public>("Ferdie")],
                 },
             )
         },
@@ -175,9 +202,22 @@ fn testnet_genesis(
         },
         pallet_sudo: SudoConfig {
             // Assign network admin rights.
-            key: root_key,
+            key: root_key.clone(),
         },
         fractal_data_capture,
+        // Short lock periods, so stakes can be tried out within a session.
+        fractal_staking: fractal_staking_config(
+            vec![(10 * MINUTES, 1), (HOURS, 2), (DAYS, 4)],
+            UNIT_BALANCE,
+        ),
+        fractal_token_distribution: fractal_token_distribution_config(vec![(
+            root_key,
+            MintQuota {
+                total_cap: 1_000_000 * UNIT_BALANCE,
+                period_allowance: 100_000 * UNIT_BALANCE,
+                period: HOURS,
+            },
+        )]),
     }
 }
 
@@ -189,6 +229,8 @@ fn mainnet_genesis(
     root_key: AccountId,
     fractal_authoritative_account: AccountId,
     seeded_accounts: Vec<(AccountId, Balance)>,
+    fractal_staking: FractalStakingConfig,
+    fractal_token_distribution: FractalTokenDistributionConfig,
 ) -> GenesisConfig {
     GenesisConfig {
         frame_system: SystemConfig {
@@ -211,7 +253,10 @@ fn mainnet_genesis(
         },
         fractal_data_capture: FractalMintingConfig {
             fractal_authoritative_account,
+            fractal_public_keys: vec![],
         },
+        fractal_staking,
+        fractal_token_distribution,
     }
 }
 
@@ -275,6 +320,10 @@ pub fn mainnet_config() -> Result<ChainSpec, String> {
                     hex!["8a871c81b98ef76617dcfe3bdc5af1b150f20553094bd98dce9da8eb4048655d"].into(),
                     5_000_000 * UNIT_BALANCE,
                 )],
+                // Lock tiers, minimum stake, issuance weights and minters are left to sudo until
+                // they're agreed, so mainnet issues nothing and accepts no stakes before then.
+                Default::default(),
+                Default::default(),
             )
         },
         // Bootnodes
@@ -7,7 +7,7 @@
 
 use std::sync::Arc;
 
-use fractal_protocol_blockchain_runtime::{opaque::Block, AccountId, Balance, Index};
+use fractal_protocol_blockchain_runtime::{opaque::Block, AccountId, Balance, BlockNumber, Index};
 pub use sc_rpc_api::DenyUnsafe;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
@@ -32,9 +32,13 @@ where
     C: Send + Sync + 'static,
     C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
     C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+    C::Api: fractal_data_capture_rpc::DataCaptureRuntimeApi<Block, AccountId>,
+    C::Api: fractal_staking_rpc::StakingRuntimeApi<Block, AccountId, BlockNumber, Balance>,
     C::Api: BlockBuilder<Block>,
     P: TransactionPool + 'static,
 {
+    use fractal_data_capture_rpc::{DataCapture, DataCaptureApi};
+    use fractal_staking_rpc::{Staking, StakingApi};
     use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
     use substrate_frame_rpc_system::{FullSystem, SystemApi};
 
@@ -52,13 +56,12 @@ where
     )));
 
     io.extend_with(TransactionPaymentApi::to_delegate(TransactionPayment::new(
-        client,
+        client.clone(),
     )));
 
-    // Extend this RPC with a custom API by using the following syntax.
-    // `YourRpcStruct` should have a reference to a client, which is needed
-    // to call into the runtime.
-    // `io.extend_with(YourRpcTrait::to_delegate(YourRpcStruct::new(ReferenceToClient, ...)));`
+    io.extend_with(DataCaptureApi::to_delegate(DataCapture::new(client.clone())));
+
+    io.extend_with(StakingApi::to_delegate(Staking::new(client)));
 
     io
 }
@@ -0,0 +1,18 @@
+//! Runtime API for querying the minting status of accounts.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+pub use fractal_data_capture::IdentityStatus;
+
+sp_api::decl_runtime_apis! {
+    pub trait DataCaptureApi<AccountId>
+    where
+        AccountId: Codec,
+    {
+        /// Status of every Fractal ID registered to `account`. Empty if the account has none.
+        fn identity_statuses(account: AccountId) -> Vec<IdentityStatus>;
+    }
+}
@@ -0,0 +1,81 @@
+//! RPC interface for the data capture pallet.
+
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use fractal_data_capture_rpc_runtime_api::{
+    DataCaptureApi as DataCaptureRuntimeApi, IdentityStatus,
+};
+
+pub use self::gen_client::Client as DataCaptureClient;
+
+#[rpc]
+pub trait DataCaptureApi<BlockHash, AccountId> {
+    /// Fractal IDs registered to `account`, with their datasets and whether they are registered
+    /// for the current minting period.
+    #[rpc(name = "fractal_identityStatuses")]
+    fn identity_statuses(
+        &self,
+        account: AccountId,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<IdentityStatus>>;
+}
+
+/// Implements `DataCaptureApi` by calling into the runtime.
+pub struct DataCapture<C, B> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> DataCapture<C, B> {
+    pub fn new(client: Arc<C>) -> Self {
+        DataCapture {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+/// Error codes returned by the data capture RPC.
+pub enum Error {
+    RuntimeError,
+}
+
+impl From<Error> for i64 {
+    fn from(e: Error) -> i64 {
+        match e {
+            Error::RuntimeError => 1,
+        }
+    }
+}
+
+impl<C, Block, AccountId> DataCaptureApi<<Block as BlockT>::Hash, AccountId>
+    for DataCapture<C, Block>
+where
+    Block: BlockT,
+    C: Send + Sync + 'static,
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+    C::Api: DataCaptureRuntimeApi<Block, AccountId>,
+    AccountId: Codec,
+{
+    fn identity_statuses(
+        &self,
+        account: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<IdentityStatus>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.identity_statuses(&at, account).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError.into()),
+            message: "Unable to query identity statuses.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+}
@@ -2,6 +2,11 @@
 
 pub use pallet::*;
 
+pub mod migrations;
+mod signed_extension;
+
+pub use signed_extension::CheckDataCapture;
+
 #[cfg(test)]
 mod mock;
 
@@ -15,18 +20,205 @@ pub mod pallet {
 
     use blake2::Blake2b;
 
-    use core::convert::TryInto;
     use frame_support::{
-        traits::{Currency, Get, Imbalance},
+        inherent::Vec,
+        traits::{Currency, ExistenceRequirement, Get, Randomness},
         weights::Weight,
     };
+    use fractal_staking::{IsVerified, OnVerificationChanged, StakeInspector, StakeLocks};
     use frame_system::ensure_signed;
     use merklex::MerkleTree;
-    use sp_runtime::traits::CheckedDiv;
+    #[cfg(feature = "std")]
+    use serde::{Deserialize, Serialize};
+    use sp_core::{sr25519, H512};
+    use sp_runtime::traits::{CheckedDiv, IntegerSquareRoot, Saturating, Verify};
 
     pub type FractalId = u64;
+    pub type MintingPeriod = u32;
+
+    /// Domain separator prepended to attestations before signing, so that Fractal signatures
+    /// can't be replayed as signatures over some other payload.
+    pub const ATTESTATION_CONTEXT: &[u8] = b"fractal-identity-attestation";
+
+    /// A statement from Fractal that `account` holds `fractal_id`, usable until `expires_at`.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    pub struct IdentityAttestation<AccountId, BlockNumber> {
+        pub fractal_id: FractalId,
+        pub account: AccountId,
+        pub expires_at: BlockNumber,
+    }
+
+    impl<AccountId: Encode, BlockNumber: Encode> IdentityAttestation<AccountId, BlockNumber> {
+        /// The bytes a Fractal key signs to produce a valid attestation.
+        pub fn signing_payload(&self) -> Vec<u8> {
+            (ATTESTATION_CONTEXT, self).encode()
+        }
+    }
+
+    /// What the runtime keeps of a dataset: enough to check that a submitted tree extends it.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    pub struct DatasetCommitment {
+        pub root: H512,
+        /// Number of leaves in the dataset, counting each pruned subtree it was submitted with as
+        /// a single leaf. `None` for datasets stored before leaves were counted.
+        pub leaf_count: Option<u32>,
+        /// Roots of the balanced subtrees the dataset is made of, largest first, or its
+        /// `MerkleTree::frontier` if the leaf count is unknown.
+        pub frontier: Vec<H512>,
+    }
+
+    impl DatasetCommitment {
+        /// Commitment to `tree` as an extension of `base`, if it is one, or as a new dataset. Only
+        /// leaves `tree` reveals beyond `base` are added, see `MerkleTree::appended_to`, so a
+        /// dataset is extended by pushing new leaves onto the tree it was last submitted as.
+        ///
+        /// A base of unknown size is measured afresh, from the leaves of `tree`.
+        pub fn new(tree: &MerkleTree<Blake2b>, base: Option<&Self>) -> Option<Self> {
+            let (base_count, base_peaks) = match base {
+                Some(DatasetCommitment {
+                    leaf_count: Some(count),
+                    frontier,
+                    ..
+                }) => (*count as usize, &frontier[..]),
+                _ => (0, &[][..]),
+            };
+            let (appended, peaks) = tree.appended_to(base_count, base_peaks)?;
+
+            Some(DatasetCommitment {
+                root: H512::from_slice(tree.hash()),
+                leaf_count: Some((base_count + appended) as u32),
+                frontier: peaks.into_iter().map(H512::from_slice).collect(),
+            })
+        }
+
+        /// The root of the balanced subtree holding the `index`th leaf, the leaf's depth below it
+        /// and its index within it.
+        pub fn subtree_of(&self, index: u32) -> Option<(H512, u32, u32)> {
+            let leaf_count = self.leaf_count?;
+            let depths = (0..32).rev().filter(|bit| leaf_count >> bit & 1 == 1);
+
+            let mut start = 0;
+            for (root, depth) in self.frontier.iter().zip(depths) {
+                if index - start < 1 << depth {
+                    return Some((*root, depth, index - start));
+                }
+                start += 1 << depth;
+            }
+
+            None
+        }
+
+        /// Whether `tree` is a different tree that extends the committed one.
+        pub fn is_strictly_extended_by(&self, tree: &MerkleTree<Blake2b>) -> bool {
+            MerkleTree::<Blake2b>::from_frontier(&self.frontier)
+                .map_or(false, |committed| tree.strict_extends(&committed))
+        }
+    }
+
+    /// Domain separator for the randomness used to pick the leaf a data challenge asks for.
+    pub const CHALLENGE_CONTEXT: &[u8] = b"fractal-data-challenge";
+
+    /// Blocks after a data challenge is made that its leaf is picked in. Randomness read in a
+    /// block may only cover the blocks before it, so this is the first block whose randomness
+    /// depends on the block holding the challenge.
+    pub const CHALLENGE_PICK_DELAY: u32 = 2;
+
+    /// A request for the owner of a dataset to prove they still hold the data behind one of its
+    /// leaves.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    pub struct DataChallenge<BlockNumber> {
+        /// The challenged leaf, once it's picked `CHALLENGE_PICK_DELAY` blocks after the
+        /// challenge.
+        pub leaf: Option<ChallengedLeaf>,
+        /// First block in which the challenge can no longer be answered.
+        pub expires_at: BlockNumber,
+    }
+
+    /// The leaf of a dataset a data challenge asks for.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    pub struct ChallengedLeaf {
+        /// Index of the challenged leaf in the dataset.
+        pub leaf_index: u32,
+        /// Root of the balanced subtree of the dataset that holds the leaf.
+        pub subtree: H512,
+        /// Depth of the leaf below `subtree`.
+        pub depth: u32,
+        /// Index of the leaf within `subtree`.
+        pub subtree_index: u32,
+    }
+
+    /// A minting period whose rewards are being paid out over multiple blocks.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    pub struct Payout<Balance> {
+        pub per_unit: Balance,
+        /// Reward units not yet paid, one per registrant unless rewards are weighted.
+        pub remaining: u32,
+    }
+
+    /// Stake an account must hold in `fractal_staking` to register for minting.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    pub struct StakeRequirement<Balance, BlockNumber> {
+        pub min_amount: Balance,
+        /// Blocks after registering that the stake must remain locked for.
+        pub lock_period: BlockNumber,
+    }
+
+    /// How the holding account is divided between registrants of a minting period.
+    #[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum RewardMode {
+        /// Every registered identity receives the same share.
+        Even,
+        /// Identities are weighted by the integer square root of the leaves their registration
+        /// added to their dataset, capped at `MaxGrowthWeightPerUser`.
+        ///
+        /// Only new leaves the extension proof reveals are counted, each pruned subtree as a
+        /// single leaf, so a registration can't claim more growth than it submits. A dataset's
+        /// first registration, or the first since its size became unknown, adds nothing and only
+        /// sets what later registrations are measured from.
+        DatasetGrowth,
+    }
+
+    impl Default for RewardMode {
+        fn default() -> Self {
+            RewardMode::Even
+        }
+    }
+
+    /// Minting status of one of an account's Fractal IDs, as returned by `DataCaptureApi`.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+    pub struct IdentityStatus {
+        pub fractal_id: FractalId,
+        /// Whether the identity is neither revoked nor expired.
+        pub active: bool,
+        pub dataset_root: Option<H512>,
+        pub leaf_count: Option<u32>,
+        /// Whether the account is registered to receive this identity's reward for the current
+        /// minting period.
+        pub registered_for_minting: bool,
+    }
+
+    #[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Releases {
+        V1,
+        /// `NextMintingRewards` is keyed by `MintingPeriod`.
+        V2,
+        /// Reward units are tracked in `NextMintingRewardUnits`, and `AccountIdDatasets` stores
+        /// `DatasetCommitment`s.
+        V3,
+        /// Accounts are indexed by Fractal ID in `IdentityAccounts`, and expiries by block in
+        /// `ExpiringIdentities`.
+        V4,
+    }
 
-    type BalanceOf<T> = <<T as fractal_token_distribution::Config>::Currency as Currency<
+    impl Default for Releases {
+        fn default() -> Self {
+            Releases::V1
+        }
+    }
+
+    pub(crate) type BalanceOf<T> = <<T as fractal_token_distribution::Config>::Currency as Currency<
         <T as frame_system::Config>::AccountId,
     >>::Balance;
 
@@ -36,6 +228,24 @@ pub mod pallet {
 
         type MaxRewardPerUser: Get<BalanceOf<Self>>;
         type MintEveryNBlocks: Get<Self::BlockNumber>;
+        /// Upper bound on the number of accounts paid in a single block.
+        type MaxPayoutsPerBlock: Get<u32>;
+        /// Upper bound on the reward units a single identity earns in `RewardMode::DatasetGrowth`.
+        type MaxGrowthWeightPerUser: Get<u32>;
+
+        type Randomness: Randomness<Self::Hash, Self::BlockNumber>;
+        /// Number of blocks a dataset owner has to answer a data challenge.
+        type ChallengeResponseBlocks: Get<Self::BlockNumber>;
+        /// Upper bound on the number of challenges expiring in a single block.
+        type MaxChallengesPerBlock: Get<u32>;
+
+        /// Told when accounts gain or lose an identity, for `fractal_staking`'s verified bonus.
+        type Staking: StakeInspector<Self::AccountId, Self::BlockNumber, BalanceOf<Self>>
+            + OnVerificationChanged<Self::AccountId>;
+        /// Upper bound on the number of accounts registered to a single Fractal ID.
+        type MaxAccountsPerIdentity: Get<u32>;
+        /// Upper bound on the number of identities expiring in a single block.
+        type MaxExpiriesPerBlock: Get<u32>;
 
         type HoldingAccount: Get<Self::AccountId>;
     }
@@ -48,8 +258,53 @@ pub mod pallet {
     pub type TotalAlreadyMinted<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
 
     #[pallet::storage]
-    pub type NextMintingRewards<T: Config> =
-        StorageMap<_, Blake2_128Concat, FractalId, T::AccountId, ValueQuery>;
+    pub(super) type StorageVersion<T: Config> = StorageValue<_, Releases, ValueQuery>;
+
+    #[pallet::storage]
+    pub type CurrentMintingPeriod<T: Config> = StorageValue<_, MintingPeriod, ValueQuery>;
+
+    #[pallet::storage]
+    pub type NextMintingRewards<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        MintingPeriod,
+        Blake2_128Concat,
+        FractalId,
+        T::AccountId,
+        OptionQuery,
+    >;
+
+    /// Number of identities registered in `NextMintingRewards` for the current period.
+    #[pallet::storage]
+    pub type NextMintingRewardsCount<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// Total reward units of the identities registered for the current period.
+    #[pallet::storage]
+    pub type NextMintingRewardUnits<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// Leaves added to their datasets in a period by identities registered under
+    /// `RewardMode::DatasetGrowth`.
+    #[pallet::storage]
+    pub type NextMintingGrowth<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        MintingPeriod,
+        Blake2_128Concat,
+        FractalId,
+        u32,
+        OptionQuery,
+    >;
+
+    #[pallet::storage]
+    pub type MintingRewardMode<T: Config> = StorageValue<_, RewardMode, ValueQuery>;
+
+    #[pallet::storage]
+    pub type ActivePayouts<T: Config> =
+        StorageMap<_, Twox64Concat, MintingPeriod, Payout<BalanceOf<T>>, OptionQuery>;
+
+    /// Balance of the holding account already promised to registrants of `ActivePayouts`.
+    #[pallet::storage]
+    pub type OutstandingPayouts<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
 
     #[pallet::storage]
     pub type AccountIds<T: Config> = StorageDoubleMap<
@@ -62,6 +317,36 @@ pub mod pallet {
         ValueQuery,
     >;
 
+    /// The accounts in `AccountIds` of each Fractal ID.
+    #[pallet::storage]
+    pub type IdentityAccounts<T: Config> =
+        StorageMap<_, Blake2_128Concat, FractalId, Vec<T::AccountId>, ValueQuery>;
+
+    /// Latest expiry of the attestations used to register each account to a Fractal ID. An
+    /// attestation only registers once, so registering the account again takes a later one.
+    #[pallet::storage]
+    pub type UsedAttestations<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        FractalId,
+        T::BlockNumber,
+        OptionQuery,
+    >;
+
+    /// Accounts that migrated away from a Fractal ID, which can't be registered to it again.
+    #[pallet::storage]
+    pub type MigratedAccounts<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        FractalId,
+        (),
+        OptionQuery,
+    >;
+
     #[pallet::storage]
     pub type AccountIdDatasets<T: Config> = StorageDoubleMap<
         _,
@@ -69,16 +354,87 @@ pub mod pallet {
         T::AccountId,
         Blake2_128Concat,
         FractalId,
-        MerkleTree<Blake2b>,
+        DatasetCommitment,
+        OptionQuery,
+    >;
+
+    #[pallet::storage]
+    pub type MintingStakeRequirement<T: Config> =
+        StorageValue<_, StakeRequirement<BalanceOf<T>, T::BlockNumber>, OptionQuery>;
+
+    /// Block until which each account that registered under `MintingStakeRequirement` must keep
+    /// the required amount staked, and the amount.
+    #[pallet::storage]
+    pub type MintingStakeLocks<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        (T::BlockNumber, BalanceOf<T>),
+        OptionQuery,
+    >;
+
+    #[pallet::storage]
+    pub type DataChallenges<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        FractalId,
+        DataChallenge<T::BlockNumber>,
         OptionQuery,
     >;
 
+    /// Challenges by the block in which they expire.
+    #[pallet::storage]
+    pub type ChallengeExpiries<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Vec<(T::AccountId, FractalId)>,
+        ValueQuery,
+    >;
+
+    /// Challenges by the block in which their leaf is picked. These are the challenges of a
+    /// single block, so `MaxChallengesPerBlock` bounds them as well.
+    #[pallet::storage]
+    pub type ChallengesToPick<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Vec<(T::AccountId, FractalId)>,
+        ValueQuery,
+    >;
+
+    /// Identities that failed a data challenge, and the minting period they're excluded from.
+    #[pallet::storage]
+    pub type FailedChallenges<T: Config> =
+        StorageMap<_, Blake2_128Concat, FractalId, MintingPeriod, OptionQuery>;
+
     #[pallet::storage]
     pub type FractalAuthoritativeAccount<T: Config> = StorageValue<_, T::AccountId, ValueQuery>;
 
+    #[pallet::storage]
+    pub type FractalPublicKeys<T: Config> =
+        StorageMap<_, Blake2_128Concat, sr25519::Public, (), OptionQuery>;
+
+    #[pallet::storage]
+    pub type RevokedIdentities<T: Config> =
+        StorageMap<_, Blake2_128Concat, FractalId, (), OptionQuery>;
+
+    #[pallet::storage]
+    pub type IdentityExpiries<T: Config> =
+        StorageMap<_, Blake2_128Concat, FractalId, T::BlockNumber, OptionQuery>;
+
+    /// Identities by the block in which they expire, to tell staking their accounts are no longer
+    /// verified.
+    #[pallet::storage]
+    pub type ExpiringIdentities<T: Config> =
+        StorageMap<_, Twox64Concat, T::BlockNumber, Vec<FractalId>, ValueQuery>;
+
     #[pallet::genesis_config]
     pub struct GenesisConfig<T: Config> {
         pub fractal_authoritative_account: T::AccountId,
+        pub fractal_public_keys: Vec<sr25519::Public>,
     }
 
     #[cfg(feature = "std")]
@@ -86,6 +442,7 @@ pub mod pallet {
         fn default() -> Self {
             GenesisConfig {
                 fractal_authoritative_account: T::AccountId::default(),
+                fractal_public_keys: Vec::new(),
             }
         }
     }
@@ -96,22 +453,110 @@ pub mod pallet {
         T::AccountId: Clone,
     {
         fn build(&self) {
+            StorageVersion::<T>::put(Releases::V4);
             FractalAuthoritativeAccount::<T>::put(self.fractal_authoritative_account.clone());
+
+            for key in &self.fractal_public_keys {
+                FractalPublicKeys::<T>::insert(key, ());
+            }
         }
     }
 
     #[pallet::event]
-    #[pallet::metadata(BalanceOf<T> = "Balance")]
+    #[pallet::metadata(
+        BalanceOf<T> = "Balance",
+        T::AccountId = "AccountId",
+        T::BlockNumber = "BlockNumber"
+    )]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
-        /// Some amount of balance was minted among the number of provided accounts.
-        /// [total, per_user, number_of_accounts, excess]
+        /// Some amount of balance was minted among the number of provided accounts. `per_user` is
+        /// paid per reward unit, which is one per account unless rewards are weighted.
+        /// [period, total, per_user, number_of_accounts, excess]
         Minted {
+            period: MintingPeriod,
             total: BalanceOf<T>,
             per_user: BalanceOf<T>,
             number_of_accounts: u32,
             excess: BalanceOf<T>,
         },
+        /// An account was paid its share of a minting period. [account, fractal_id, period, amount]
+        PaidOut {
+            account: T::AccountId,
+            fractal_id: FractalId,
+            period: MintingPeriod,
+            amount: BalanceOf<T>,
+        },
+        /// [fractal_id, account]
+        IdentityRegistered {
+            fractal_id: FractalId,
+            account: T::AccountId,
+        },
+        /// [fractal_id]
+        IdentityRevoked { fractal_id: FractalId },
+        /// [fractal_id, expires_at]
+        IdentityExpirySet {
+            fractal_id: FractalId,
+            expires_at: Option<T::BlockNumber>,
+        },
+        /// [fractal_id, from, to]
+        IdentityMigrated {
+            fractal_id: FractalId,
+            from: T::AccountId,
+            to: T::AccountId,
+        },
+        /// [key, allowed]
+        FractalPublicKeySet {
+            key: sr25519::Public,
+            allowed: bool,
+        },
+        /// A dataset was stored for the first time or replaced by an extension of itself.
+        /// [account, fractal_id, old_root, new_root, old_leaf_count, new_leaf_count]
+        DatasetExtended {
+            account: T::AccountId,
+            fractal_id: FractalId,
+            old_root: Option<H512>,
+            new_root: H512,
+            old_leaf_count: Option<u32>,
+            new_leaf_count: Option<u32>,
+        },
+        /// [account, fractal_id, period]
+        RegisteredForMinting {
+            account: T::AccountId,
+            fractal_id: FractalId,
+            period: MintingPeriod,
+        },
+        /// [mode]
+        RewardModeSet { mode: RewardMode },
+        /// [requirement]
+        StakeRequirementSet {
+            requirement: Option<StakeRequirement<BalanceOf<T>, T::BlockNumber>>,
+        },
+        /// [account, fractal_id, amount]
+        DatasetSlashed {
+            account: T::AccountId,
+            fractal_id: FractalId,
+            amount: BalanceOf<T>,
+        },
+        /// The leaf a data challenge asks for was picked.
+        /// [account, fractal_id, leaf_index, expires_at]
+        DataChallenged {
+            account: T::AccountId,
+            fractal_id: FractalId,
+            leaf_index: u32,
+            expires_at: T::BlockNumber,
+        },
+        /// [account, fractal_id]
+        ChallengeAnswered {
+            account: T::AccountId,
+            fractal_id: FractalId,
+        },
+        /// The identity is excluded from minting in `period`. [account, fractal_id, period]
+        ChallengeFailed {
+            account: T::AccountId,
+            fractal_id: FractalId,
+            period: MintingPeriod,
+        },
     }
 
     #[pallet::error]
@@ -121,12 +566,41 @@ pub mod pallet {
         MustSpecifyFractalIdWithMultipleIds,
         FractalIdNotRegisteredToAccount,
         MustBeFractal,
+        AttestationForDifferentAccount,
+        AttestationExpired,
+        UnknownFractalKey,
+        InvalidAttestationSignature,
+        IdentityRevoked,
+        IdentityExpired,
+        DatasetAlreadyExists,
+        NoDatasetToChallenge,
+        ChallengeOpen,
+        TooManyChallenges,
+        NoChallengeOpen,
+        InvalidChallengeResponse,
+        FailedDataChallenge,
+        InsufficientStake,
+        AlreadyRegisteredThisPeriod,
+        NewLeavesNotRevealed,
+        TooManyAccounts,
+        TooManyExpiries,
+        AttestationAlreadyUsed,
+        AccountMigratedAway,
+        UnknownLeafCount,
+        CannotChallengeOwnDataset,
+        ChallengedLeafNotPicked,
+        AlreadyRegisteredToFractalId,
     }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
+        /// Register `account` to `fractal_id`. Registering a revoked identity again reinstates
+        /// it, along with its other accounts.
         #[pallet::weight((
-            10_000 + T::DbWeight::get().reads_writes(1, 1),
+            10_000
+                + T::DbWeight::get().reads_writes(6, 5)
+                + T::Staking::on_verification_changed_weight()
+                    * T::MaxAccountsPerIdentity::get() as Weight,
             DispatchClass::Normal,
             Pays::No
         ))]
@@ -138,24 +612,320 @@ pub mod pallet {
         where
             T::AccountId: Clone,
         {
-            let should_be_fractal = ensure_signed(origin)?;
+            Self::ensure_fractal(origin)?;
             ensure!(
-                should_be_fractal == FractalAuthoritativeAccount::<T>::get(),
-                Error::<T>::MustBeFractal
+                !MigratedAccounts::<T>::contains_key(&account, fractal_id),
+                Error::<T>::AccountMigratedAway
             );
+            let accounts = Self::accounts_with(fractal_id, &account)?;
+
+            let reinstated = RevokedIdentities::<T>::take(fractal_id).is_some();
+            AccountIds::<T>::insert(account.clone(), fractal_id, ());
+            IdentityAccounts::<T>::insert(fractal_id, &accounts);
+            if reinstated {
+                Self::verification_changed(&accounts);
+            } else {
+                T::Staking::on_verification_changed(&account);
+            }
+
+            Self::deposit_event(Event::IdentityRegistered {
+                fractal_id,
+                account,
+            });
+
+            Ok(())
+        }
+
+        /// Register the signer's account to a Fractal ID using an attestation signed offline by
+        /// one of the `FractalPublicKeys`. Each attestation can only be used once, and the account
+        /// can't already be registered to the Fractal ID.
+        #[pallet::weight((
+            50_000
+                + T::DbWeight::get().reads_writes(10, 5)
+                + T::Staking::on_verification_changed_weight(),
+            DispatchClass::Normal,
+            Pays::No
+        ))]
+        pub fn register_identity_signed(
+            origin: OriginFor<T>,
+            attestation: IdentityAttestation<T::AccountId, BlockNumberFor<T>>,
+            signer: sr25519::Public,
+            signature: sr25519::Signature,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::validate_attestation(&who, &attestation, &signer, &signature)?;
+            let accounts = Self::accounts_with(attestation.fractal_id, &attestation.account)?;
+
+            AccountIds::<T>::insert(&attestation.account, attestation.fractal_id, ());
+            IdentityAccounts::<T>::insert(attestation.fractal_id, accounts);
+            UsedAttestations::<T>::insert(
+                &attestation.account,
+                attestation.fractal_id,
+                attestation.expires_at,
+            );
+            T::Staking::on_verification_changed(&attestation.account);
+
+            Self::deposit_event(Event::IdentityRegistered {
+                fractal_id: attestation.fractal_id,
+                account: attestation.account,
+            });
+
+            Ok(())
+        }
+
+        #[pallet::weight((
+            10_000 + T::DbWeight::get().reads_writes(0, 1),
+            DispatchClass::Normal,
+            Pays::No
+        ))]
+        pub fn set_fractal_public_key(
+            origin: OriginFor<T>,
+            key: sr25519::Public,
+            should_allow: bool,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            if should_allow {
+                FractalPublicKeys::<T>::insert(key, ());
+            } else {
+                FractalPublicKeys::<T>::remove(key);
+            }
+
+            Self::deposit_event(Event::FractalPublicKeySet {
+                key,
+                allowed: should_allow,
+            });
+
+            Ok(())
+        }
+
+        /// Choose how future minting periods are divided between registrants. Registrations
+        /// already made in the current period keep the weight they were given.
+        #[pallet::weight((
+            10_000 + T::DbWeight::get().reads_writes(0, 1),
+            DispatchClass::Normal,
+            Pays::No
+        ))]
+        pub fn set_reward_mode(origin: OriginFor<T>, mode: RewardMode) -> DispatchResult {
+            ensure_root(origin)?;
+
+            MintingRewardMode::<T>::put(mode);
+
+            Self::deposit_event(Event::RewardModeSet { mode });
+
+            Ok(())
+        }
+
+        /// Require accounts to stake on their data being genuine before registering for minting,
+        /// or stop requiring it with `None`.
+        #[pallet::weight((
+            10_000 + T::DbWeight::get().reads_writes(0, 1),
+            DispatchClass::Normal,
+            Pays::No
+        ))]
+        pub fn set_stake_requirement(
+            origin: OriginFor<T>,
+            requirement: Option<StakeRequirement<BalanceOf<T>, T::BlockNumber>>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            MintingStakeRequirement::<T>::set(requirement.clone());
+
+            Self::deposit_event(Event::StakeRequirementSet { requirement });
+
+            Ok(())
+        }
+
+        /// Slash up to `amount` of the stake of an account whose dataset for `fractal_id` was
+        /// judged fraudulent. The slashed funds go to the holding account to be minted to other
+        /// users, and the identity loses any pending minting reward.
+        #[pallet::weight((
+            50_000 + T::DbWeight::get().reads_writes(7, 6),
+            DispatchClass::Normal,
+            Pays::No
+        ))]
+        pub fn slash_fraudulent_dataset(
+            origin: OriginFor<T>,
+            account: T::AccountId,
+            fractal_id: FractalId,
+            amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(
+                AccountIds::<T>::contains_key(&account, fractal_id),
+                Error::<T>::FractalIdNotRegisteredToAccount
+            );
+
+            let amount = T::Staking::slash(&account, amount, &T::HoldingAccount::get());
+            Self::remove_pending_reward(fractal_id);
+
+            Self::deposit_event(Event::DatasetSlashed {
+                account,
+                fractal_id,
+                amount,
+            });
+
+            Ok(())
+        }
 
-            AccountIds::<T>::insert(account, fractal_id, ());
+        /// Revoke a Fractal ID, e.g. when its KYC lapses. The identity can no longer register
+        /// for minting, loses any pending minting reward, and its accounts lose any staking bonus.
+        #[pallet::weight((
+            10_000
+                + T::DbWeight::get().reads_writes(4, 5)
+                + T::Staking::on_verification_changed_weight()
+                    * T::MaxAccountsPerIdentity::get() as Weight,
+            DispatchClass::Normal,
+            Pays::No
+        ))]
+        pub fn revoke_identity(origin: OriginFor<T>, fractal_id: FractalId) -> DispatchResult {
+            Self::ensure_fractal(origin)?;
+
+            RevokedIdentities::<T>::insert(fractal_id, ());
+            Self::remove_pending_reward(fractal_id);
+            Self::verification_changed(&IdentityAccounts::<T>::get(fractal_id));
+
+            Self::deposit_event(Event::IdentityRevoked { fractal_id });
 
             Ok(())
         }
 
-        /// Register to receive minting in the next period.
-        // TODO(shelbyd): Charge users transaction fees if this isn't their first registration.
+        /// Set the block from which a Fractal ID can no longer register for minting, and its
+        /// accounts lose any staking bonus.
         #[pallet::weight((
-            10_000 + T::DbWeight::get().reads_writes(3, 2),
+            10_000
+                + T::DbWeight::get().reads_writes(4, 3)
+                + T::Staking::on_verification_changed_weight()
+                    * T::MaxAccountsPerIdentity::get() as Weight,
             DispatchClass::Normal,
             Pays::No
         ))]
+        pub fn set_identity_expiry(
+            origin: OriginFor<T>,
+            fractal_id: FractalId,
+            expires_at: Option<T::BlockNumber>,
+        ) -> DispatchResult {
+            Self::ensure_fractal(origin)?;
+
+            let now = <frame_system::Pallet<T>>::block_number();
+            let unscheduled = |block| {
+                let mut expiring = ExpiringIdentities::<T>::get(block);
+                expiring.retain(|id| *id != fractal_id);
+                expiring
+            };
+            let scheduled = match expires_at.filter(|expires_at| *expires_at > now) {
+                Some(expires_at) => {
+                    let mut expiring = unscheduled(expires_at);
+                    ensure!(
+                        (expiring.len() as u32) < T::MaxExpiriesPerBlock::get(),
+                        Error::<T>::TooManyExpiries
+                    );
+                    expiring.push(fractal_id);
+                    Some((expires_at, expiring))
+                }
+                None => None,
+            };
+
+            if let Some(previous) = IdentityExpiries::<T>::get(fractal_id).filter(|at| *at > now) {
+                ExpiringIdentities::<T>::insert(previous, unscheduled(previous));
+            }
+            if let Some((expires_at, expiring)) = scheduled {
+                ExpiringIdentities::<T>::insert(expires_at, expiring);
+            }
+            match expires_at {
+                Some(expires_at) => IdentityExpiries::<T>::insert(fractal_id, expires_at),
+                None => IdentityExpiries::<T>::remove(fractal_id),
+            }
+            // An expiry in the past applies at once, and a later or removed one may reinstate the
+            // identity.
+            Self::verification_changed(&IdentityAccounts::<T>::get(fractal_id));
+
+            Self::deposit_event(Event::IdentityExpirySet {
+                fractal_id,
+                expires_at,
+            });
+
+            Ok(())
+        }
+
+        /// Move an identity, with its dataset and any pending minting reward, from `from` to the
+        /// signer.
+        ///
+        /// The signer must already be registered to `fractal_id`, so a user who lost the key to
+        /// `from` first registers a new account with Fractal and then migrates to it. `from` can't
+        /// be registered to `fractal_id` again, so whoever holds its key can't take the identity
+        /// back.
+        #[pallet::weight(
+            10_000
+                + T::DbWeight::get().reads_writes(12, 10)
+                + T::Staking::on_verification_changed_weight()
+        )]
+        pub fn migrate_identity(
+            origin: OriginFor<T>,
+            fractal_id: FractalId,
+            from: T::AccountId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(
+                AccountIds::<T>::contains_key(&who, fractal_id)
+                    && AccountIds::<T>::contains_key(&from, fractal_id),
+                Error::<T>::FractalIdNotRegisteredToAccount
+            );
+            Self::ensure_identity_active(fractal_id)?;
+            ensure!(
+                !AccountIdDatasets::<T>::contains_key(&who, fractal_id),
+                Error::<T>::DatasetAlreadyExists
+            );
+            ensure!(
+                !DataChallenges::<T>::contains_key(&from, fractal_id),
+                Error::<T>::ChallengeOpen
+            );
+
+            AccountIds::<T>::remove(&from, fractal_id);
+            MigratedAccounts::<T>::insert(&from, fractal_id, ());
+            IdentityAccounts::<T>::mutate(fractal_id, |accounts| {
+                accounts.retain(|account| *account != from)
+            });
+            T::Staking::on_verification_changed(&from);
+            if let Some(dataset) = AccountIdDatasets::<T>::take(&from, fractal_id) {
+                AccountIdDatasets::<T>::insert(&who, fractal_id, dataset);
+            }
+            let pending_periods = ActivePayouts::<T>::iter()
+                .map(|(period, _)| period)
+                .chain(core::iter::once(CurrentMintingPeriod::<T>::get()));
+            for period in pending_periods {
+                NextMintingRewards::<T>::mutate_exists(period, fractal_id, |account| {
+                    if account.as_ref() == Some(&from) {
+                        *account = Some(who.clone());
+                    }
+                });
+            }
+
+            Self::deposit_event(Event::IdentityMigrated {
+                fractal_id,
+                from,
+                to: who,
+            });
+
+            Ok(())
+        }
+
+        /// Register to receive minting in the next period, extending the dataset with the leaves
+        /// `extension_proof` reveals. See `DatasetCommitment::new`.
+        ///
+        /// Only the first registration of a dataset is free: the fee is charged up front and
+        /// refunded for it. `CheckDataCapture` keeps calls that would fail, and repeat
+        /// registrations within a period, out of blocks. A repeat registration replaces the
+        /// earlier one.
+        ///
+        /// The stake `MintingStakeRequirement` asks for can't be unstaked early until its lock
+        /// period after registering ends.
+        #[pallet::weight((
+            10_000 + T::DbWeight::get().reads_writes(13, 6),
+            DispatchClass::Normal,
+            Pays::Yes
+        ))]
         pub fn register_for_minting(
             origin: OriginFor<T>,
             identity: Option<FractalId>,
@@ -163,16 +933,241 @@ pub mod pallet {
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
 
+            let (id, id_datasets_entry, commitment) =
+                Self::validate_registration(&who, identity, &extension_proof)?;
+            let period = CurrentMintingPeriod::<T>::get();
+
+            let previous_leaf_count = id_datasets_entry
+                .as_ref()
+                .and_then(|existing| existing.leaf_count);
+            // Datasets of unknown size are measured afresh, adding nothing.
+            let growth = previous_leaf_count.map_or(0, |previous| {
+                commitment
+                    .leaf_count
+                    .unwrap_or_default()
+                    .saturating_sub(previous)
+            });
+            Self::deposit_event(Event::DatasetExtended {
+                account: who.clone(),
+                fractal_id: id,
+                old_root: id_datasets_entry.as_ref().map(|existing| existing.root),
+                new_root: commitment.root,
+                old_leaf_count: previous_leaf_count,
+                new_leaf_count: commitment.leaf_count,
+            });
+            AccountIdDatasets::<T>::insert(who.clone(), id, commitment);
+            if let Some(requirement) = MintingStakeRequirement::<T>::get() {
+                // `validate_registration` checked the stake, which now can't be unstaked early.
+                let locked_until =
+                    <frame_system::Pallet<T>>::block_number() + requirement.lock_period;
+                MintingStakeLocks::<T>::mutate(&who, |lock| match lock {
+                    Some((until, _)) if *until > locked_until => {}
+                    _ => *lock = Some((locked_until, requirement.min_amount)),
+                });
+            }
+
+            let previous_growth = NextMintingGrowth::<T>::get(period, id);
+            let previous_units = if NextMintingRewards::<T>::contains_key(period, id) {
+                Self::reward_units(previous_growth)
+            } else {
+                NextMintingRewardsCount::<T>::mutate(|count| *count = count.saturating_add(1));
+                0
+            };
+
+            let units = match MintingRewardMode::<T>::get() {
+                RewardMode::Even => Self::reward_units(previous_growth),
+                RewardMode::DatasetGrowth => {
+                    NextMintingGrowth::<T>::insert(period, id, growth);
+                    Self::reward_units(Some(growth))
+                }
+            };
+            NextMintingRewardUnits::<T>::mutate(|total| {
+                *total = total.saturating_sub(previous_units).saturating_add(units)
+            });
+            NextMintingRewards::<T>::insert(period, id, who.clone());
+
+            Self::deposit_event(Event::RegisteredForMinting {
+                account: who,
+                fractal_id: id,
+                period,
+            });
+
+            Ok(match id_datasets_entry {
+                Some(_) => Pays::Yes.into(),
+                None => Pays::No.into(),
+            })
+        }
+
+        /// Challenge `account` to prove it still holds the data behind a random leaf of the dataset
+        /// stored for `fractal_id`. The leaf is picked `CHALLENGE_PICK_DELAY` blocks later, so
+        /// nobody knows it when challenging. If the owner doesn't answer within
+        /// `ChallengeResponseBlocks` of the pick, the identity can't receive minting for the
+        /// current period.
+        ///
+        /// Accounts registered to `fractal_id` can't challenge its datasets.
+        #[pallet::weight(10_000 + T::DbWeight::get().reads_writes(5, 3))]
+        pub fn challenge_dataset(
+            origin: OriginFor<T>,
+            account: T::AccountId,
+            fractal_id: FractalId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(
+                who != account && !AccountIds::<T>::contains_key(&who, fractal_id),
+                Error::<T>::CannotChallengeOwnDataset
+            );
+            let dataset = AccountIdDatasets::<T>::get(&account, fractal_id)
+                .ok_or(Error::<T>::NoDatasetToChallenge)?;
+            ensure!(dataset.leaf_count.is_some(), Error::<T>::UnknownLeafCount);
+            ensure!(
+                !DataChallenges::<T>::contains_key(&account, fractal_id),
+                Error::<T>::ChallengeOpen
+            );
+
+            let picked_at = <frame_system::Pallet<T>>::block_number() + CHALLENGE_PICK_DELAY.into();
+            let expires_at = picked_at + T::ChallengeResponseBlocks::get();
+            let mut expiring = ChallengeExpiries::<T>::get(expires_at);
+            ensure!(
+                (expiring.len() as u32) < T::MaxChallengesPerBlock::get(),
+                Error::<T>::TooManyChallenges
+            );
+
+            DataChallenges::<T>::insert(
+                &account,
+                fractal_id,
+                DataChallenge {
+                    leaf: None,
+                    expires_at,
+                },
+            );
+            expiring.push((account.clone(), fractal_id));
+            ChallengeExpiries::<T>::insert(expires_at, expiring);
+            ChallengesToPick::<T>::append(picked_at, (account, fractal_id));
+
+            Ok(())
+        }
+
+        /// Answer an open data challenge with the challenged leaf's data.
+        ///
+        /// `proof` must hash to the challenge's subtree, and reveal the path from it to the leaf
+        /// holding `data`. The bits of the leaf's index within the subtree pick the path, most
+        /// significant first, taking the left child for a 0 bit.
+        #[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+        pub fn respond_to_challenge(
+            origin: OriginFor<T>,
+            fractal_id: FractalId,
+            proof: MerkleTree<Blake2b>,
+            data: Vec<u8>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let leaf = DataChallenges::<T>::get(&who, fractal_id)
+                .ok_or(Error::<T>::NoChallengeOpen)?
+                .leaf
+                .ok_or(Error::<T>::ChallengedLeafNotPicked)?;
+            ensure!(
+                H512::from_slice(proof.hash()) == leaf.subtree
+                    && Self::opens_to(&proof, leaf.subtree_index, leaf.depth, &data),
+                Error::<T>::InvalidChallengeResponse
+            );
+
+            DataChallenges::<T>::remove(&who, fractal_id);
+
+            Self::deposit_event(Event::ChallengeAnswered {
+                account: who,
+                fractal_id,
+            });
+
+            Ok(())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Status of every Fractal ID registered to `account`.
+        pub fn identity_statuses(account: &T::AccountId) -> Vec<IdentityStatus> {
+            let period = CurrentMintingPeriod::<T>::get();
+
+            AccountIds::<T>::iter_prefix(account)
+                .map(|(fractal_id, ())| {
+                    let dataset = AccountIdDatasets::<T>::get(account, fractal_id);
+
+                    IdentityStatus {
+                        fractal_id,
+                        active: Self::ensure_identity_active(fractal_id).is_ok(),
+                        dataset_root: dataset.as_ref().map(|d| d.root),
+                        leaf_count: dataset.and_then(|d| d.leaf_count),
+                        registered_for_minting: NextMintingRewards::<T>::get(period, fractal_id)
+                            .as_ref()
+                            == Some(account),
+                    }
+                })
+                .collect()
+        }
+
+        /// Checks that `attestation` registers `who`, is signed by a current Fractal key and
+        /// hasn't been used.
+        pub(crate) fn validate_attestation(
+            who: &T::AccountId,
+            attestation: &IdentityAttestation<T::AccountId, BlockNumberFor<T>>,
+            signer: &sr25519::Public,
+            signature: &sr25519::Signature,
+        ) -> DispatchResult {
+            ensure!(
+                &attestation.account == who,
+                Error::<T>::AttestationForDifferentAccount
+            );
+            ensure!(
+                attestation.expires_at >= <frame_system::Pallet<T>>::block_number(),
+                Error::<T>::AttestationExpired
+            );
+            ensure!(
+                FractalPublicKeys::<T>::contains_key(signer),
+                Error::<T>::UnknownFractalKey
+            );
+            ensure!(
+                signature.verify(&attestation.signing_payload()[..], signer),
+                Error::<T>::InvalidAttestationSignature
+            );
+            ensure!(
+                UsedAttestations::<T>::get(who, attestation.fractal_id)
+                    .map_or(true, |used| attestation.expires_at > used),
+                Error::<T>::AttestationAlreadyUsed
+            );
+            ensure!(
+                !AccountIds::<T>::contains_key(who, attestation.fractal_id),
+                Error::<T>::AlreadyRegisteredToFractalId
+            );
+            ensure!(
+                !MigratedAccounts::<T>::contains_key(who, attestation.fractal_id),
+                Error::<T>::AccountMigratedAway
+            );
+            ensure!(
+                !RevokedIdentities::<T>::contains_key(attestation.fractal_id),
+                Error::<T>::IdentityRevoked
+            );
+
+            Ok(())
+        }
+
+        /// Checks that `who` can register `extension_proof` for minting, returning the Fractal ID
+        /// it registers for, the dataset it extends and the commitment that replaces it.
+        pub(crate) fn validate_registration(
+            who: &T::AccountId,
+            identity: Option<FractalId>,
+            extension_proof: &MerkleTree<Blake2b>,
+        ) -> Result<(FractalId, Option<DatasetCommitment>, DatasetCommitment), DispatchError>
+        {
             let id = match identity {
                 Some(id) => {
                     ensure!(
-                        AccountIds::<T>::contains_key(&who, &id),
+                        AccountIds::<T>::contains_key(who, &id),
                         Error::<T>::FractalIdNotRegisteredToAccount
                     );
                     id
                 }
                 None => {
-                    let mut ids = AccountIds::<T>::iter_prefix(&who);
+                    let mut ids = AccountIds::<T>::iter_prefix(who);
 
                     match (ids.next(), ids.next()) {
                         (None, _) => return Err(Error::<T>::NoIdentityRegistered.into()),
@@ -183,70 +1178,388 @@ pub mod pallet {
                     }
                 }
             };
+            Self::ensure_identity_active(id)?;
+
+            ensure!(
+                FailedChallenges::<T>::get(id) != Some(CurrentMintingPeriod::<T>::get()),
+                Error::<T>::FailedDataChallenge
+            );
+            if let Some(requirement) = MintingStakeRequirement::<T>::get() {
+                let locked_until =
+                    <frame_system::Pallet<T>>::block_number() + requirement.lock_period;
+                ensure!(
+                    T::Staking::staked_until(who, locked_until) >= requirement.min_amount,
+                    Error::<T>::InsufficientStake
+                );
+            }
 
-            let id_datasets_entry = AccountIdDatasets::<T>::get(who.clone(), id);
+            let id_datasets_entry = AccountIdDatasets::<T>::get(who, id);
             if let Some(existing) = &id_datasets_entry {
                 ensure!(
-                    extension_proof.strict_extends(existing),
+                    existing.is_strictly_extended_by(extension_proof),
                     Error::<T>::ExtensionDoesNotExtendExistingDataset
                 );
             }
+            let commitment = DatasetCommitment::new(extension_proof, id_datasets_entry.as_ref())
+                .ok_or(Error::<T>::NewLeavesNotRevealed)?;
 
-            AccountIdDatasets::<T>::insert(who.clone(), id, extension_proof);
-            NextMintingRewards::<T>::insert(id, who);
+            Ok((id, id_datasets_entry, commitment))
+        }
 
-            Ok(match id_datasets_entry {
-                Some(_) => Pays::Yes.into(),
-                None => Pays::No.into(),
+        fn ensure_fractal(origin: OriginFor<T>) -> DispatchResult {
+            if let Ok(()) = ensure_root(origin.clone()) {
+                return Ok(());
+            }
+
+            let should_be_fractal = ensure_signed(origin)?;
+            ensure!(
+                should_be_fractal == FractalAuthoritativeAccount::<T>::get(),
+                Error::<T>::MustBeFractal
+            );
+
+            Ok(())
+        }
+
+        /// Whether following the low `depth` bits of `index` down `proof`, most significant first,
+        /// reaches a leaf of `data`.
+        ///
+        /// Leaves and inner nodes are hashed the same way, so the path must be opened all the way
+        /// down: a node above the leaf would otherwise be accepted with the concatenated hashes of
+        /// its children as data.
+        fn opens_to(proof: &MerkleTree<Blake2b>, index: u32, depth: u32, data: &[u8]) -> bool {
+            let mut node = proof;
+            for level in (0..depth).rev() {
+                node = match node.children() {
+                    Some((left, _)) if index >> level & 1 == 0 => left,
+                    Some((_, right)) => right,
+                    None => return false,
+                };
+            }
+
+            node == &MerkleTree::leaf_bytes(data)
+        }
+
+        /// Removes `id` from the identities paid at the end of the current period.
+        fn remove_pending_reward(id: FractalId) {
+            let period = CurrentMintingPeriod::<T>::get();
+            if NextMintingRewards::<T>::take(period, id).is_some() {
+                let units = Self::reward_units(NextMintingGrowth::<T>::take(period, id));
+                NextMintingRewardsCount::<T>::mutate(|count| *count = count.saturating_sub(1));
+                NextMintingRewardUnits::<T>::mutate(|total| *total = total.saturating_sub(units));
+            }
+        }
+
+        fn ensure_identity_active(id: FractalId) -> DispatchResult {
+            ensure!(
+                !RevokedIdentities::<T>::contains_key(id),
+                Error::<T>::IdentityRevoked
+            );
+            if let Some(expires_at) = IdentityExpiries::<T>::get(id) {
+                ensure!(
+                    <frame_system::Pallet<T>>::block_number() < expires_at,
+                    Error::<T>::IdentityExpired
+                );
+            }
+
+            Ok(())
+        }
+
+        /// `IdentityAccounts` of `id` with `account` added, if it isn't there already.
+        fn accounts_with(
+            id: FractalId,
+            account: &T::AccountId,
+        ) -> Result<Vec<T::AccountId>, DispatchError> {
+            let mut accounts = IdentityAccounts::<T>::get(id);
+            if !accounts.contains(account) {
+                ensure!(
+                    (accounts.len() as u32) < T::MaxAccountsPerIdentity::get(),
+                    Error::<T>::TooManyAccounts
+                );
+                accounts.push(account.clone());
+            }
+
+            Ok(accounts)
+        }
+
+        /// Tells staking that `accounts` may have been verified or unverified, returning the
+        /// weight it used.
+        fn verification_changed(accounts: &[T::AccountId]) -> Weight {
+            accounts.iter().fold(0, |weight, account| {
+                weight.saturating_add(T::Staking::on_verification_changed(account))
             })
         }
+
+        /// Tells staking about the accounts of identities that expire at `block_number`.
+        fn expire_identities(block_number: BlockNumberFor<T>) -> Weight {
+            let expiring = ExpiringIdentities::<T>::take(block_number);
+            let mut weight = T::DbWeight::get().reads_writes(1, 1);
+
+            for id in expiring {
+                weight = weight.saturating_add(T::DbWeight::get().reads(2));
+                // A changed expiry was scheduled again, and a removed one isn't due.
+                if IdentityExpiries::<T>::get(id) != Some(block_number) {
+                    continue;
+                }
+
+                let accounts = IdentityAccounts::<T>::get(id);
+                weight = weight.saturating_add(Self::verification_changed(&accounts));
+            }
+
+            weight
+        }
+
+        /// Reward units of an identity that revealed `growth` new leaves this period, or one if
+        /// it only registered under `RewardMode::Even`.
+        fn reward_units(growth: Option<u32>) -> u32 {
+            match growth {
+                None => 1,
+                Some(growth) => growth
+                    .integer_sqrt()
+                    .max(1)
+                    .min(T::MaxGrowthWeightPerUser::get().max(1)),
+            }
+        }
+    }
+
+    impl<T: Config> StakeLocks<T::AccountId, T::BlockNumber, BalanceOf<T>> for Pallet<T> {
+        fn stake_lock(who: &T::AccountId) -> Option<(T::BlockNumber, BalanceOf<T>)> {
+            MintingStakeLocks::<T>::get(who)
+                .filter(|(until, _)| <frame_system::Pallet<T>>::block_number() < *until)
+        }
+    }
+
+    /// Accounts registered to a Fractal ID that's neither revoked nor expired are verified.
+    impl<T: Config> IsVerified<T::AccountId> for Pallet<T> {
+        fn is_verified(who: &T::AccountId) -> bool {
+            AccountIds::<T>::iter_prefix(who)
+                .any(|(fractal_id, ())| Self::ensure_identity_active(fractal_id).is_ok())
+        }
     }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
-        fn on_initialize(_block_number: BlockNumberFor<T>) -> Weight {
-            Weight::default()
+        fn on_initialize(block_number: BlockNumberFor<T>) -> Weight {
+            let mut weight = Self::pay_out(T::MaxPayoutsPerBlock::get());
+            weight = weight.saturating_add(Self::pick_challenged_leaves(block_number));
+            weight = weight.saturating_add(Self::expire_challenges(block_number));
+            weight = weight.saturating_add(Self::expire_identities(block_number));
+
+            if Self::is_mint_block(block_number) {
+                // Accounts for the work done in `on_finalize`.
+                weight = weight.saturating_add(T::DbWeight::get().reads_writes(7, 5));
+            }
+
+            weight
         }
 
         fn on_finalize(block_number: BlockNumberFor<T>) {
-            let is_mint_block = block_number % T::MintEveryNBlocks::get() == 0u32.into()
-                && block_number != 0u32.into();
-            if !is_mint_block {
+            if !Self::is_mint_block(block_number) {
                 return;
             }
 
-            let accounts_count = NextMintingRewards::<T>::iter()
-                .count()
-                .try_into()
-                .unwrap_or(core::u32::MAX);
+            let period = CurrentMintingPeriod::<T>::mutate(|p| {
+                let current = *p;
+                *p = p.saturating_add(1);
+                current
+            });
+            let accounts_count = NextMintingRewardsCount::<T>::take();
+            let units = NextMintingRewardUnits::<T>::take();
+
+            // Keeps the identity with the most units within `MaxRewardPerUser`.
+            let max_units_per_user = if NextMintingGrowth::<T>::iter_prefix(period)
+                .next()
+                .is_some()
+            {
+                T::MaxGrowthWeightPerUser::get().max(1)
+            } else {
+                1
+            };
 
-            let taken = T::Currency::free_balance(&T::HoldingAccount::get());
-            let even_per_user = taken
-                .checked_div(&accounts_count.into())
-                .unwrap_or_default();
-            let per_user = core::cmp::min(T::MaxRewardPerUser::get(), even_per_user);
-            let total = per_user * accounts_count.into();
+            let taken = T::Currency::free_balance(&T::HoldingAccount::get())
+                .saturating_sub(OutstandingPayouts::<T>::get());
+            let even_per_unit = taken.checked_div(&units.into()).unwrap_or_default();
+            let max_per_unit = T::MaxRewardPerUser::get() / max_units_per_user.into();
+            let per_unit = core::cmp::min(max_per_unit, even_per_unit);
+            let total = per_unit * units.into();
             let excess = taken - total;
-            T::Currency::make_free_balance_be(&T::HoldingAccount::get(), excess);
-
-            let recipients = NextMintingRewards::<T>::iter()
-                .take(accounts_count.try_into().expect("at least 32bit OS"));
-            let removed = recipients.inspect(|(id, _)| NextMintingRewards::<T>::remove(id));
-
-            // Dropping the Imbalance resolves it.
-            let _imbalance = removed
-                .map(|(_, account)| T::Currency::deposit_creating(&account, per_user))
-                .fold(
-                    <T::Currency as Currency<_>>::PositiveImbalance::zero(),
-                    |acc, v| acc.merge(v),
+
+            if accounts_count > 0 {
+                ActivePayouts::<T>::insert(
+                    period,
+                    Payout {
+                        per_unit,
+                        remaining: units,
+                    },
                 );
+                OutstandingPayouts::<T>::mutate(|o| *o = o.saturating_add(total));
+            }
 
             Self::deposit_event(Event::Minted {
+                period,
                 total,
-                per_user,
+                per_user: per_unit,
                 number_of_accounts: accounts_count,
                 excess,
             });
         }
+
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::migrate::<T>()
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        fn is_mint_block(block_number: BlockNumberFor<T>) -> bool {
+            block_number % T::MintEveryNBlocks::get() == 0u32.into()
+                && block_number != 0u32.into()
+        }
+
+        /// Picks the leaves asked for by the challenges made `CHALLENGE_PICK_DELAY` blocks before
+        /// `block_number`.
+        fn pick_challenged_leaves(block_number: BlockNumberFor<T>) -> Weight {
+            let challenged = ChallengesToPick::<T>::take(block_number);
+
+            for (account, id) in &challenged {
+                let mut challenge = match DataChallenges::<T>::get(account, id) {
+                    Some(challenge) => challenge,
+                    None => continue,
+                };
+                let (seed, _) =
+                    T::Randomness::random(&(CHALLENGE_CONTEXT, account, id, block_number).encode());
+                let leaf = match AccountIdDatasets::<T>::get(account, id)
+                    .and_then(|dataset| Self::pick_leaf(&dataset, seed.as_ref()))
+                {
+                    Some(leaf) => leaf,
+                    // There's nothing to answer without a leaf to ask for.
+                    None => {
+                        DataChallenges::<T>::remove(account, id);
+                        continue;
+                    }
+                };
+
+                Self::deposit_event(Event::DataChallenged {
+                    account: account.clone(),
+                    fractal_id: *id,
+                    leaf_index: leaf.leaf_index,
+                    expires_at: challenge.expires_at,
+                });
+                challenge.leaf = Some(leaf);
+                DataChallenges::<T>::insert(account, id, challenge);
+            }
+
+            T::DbWeight::get().reads_writes(1, 1)
+                + T::DbWeight::get().reads_writes(3, 1) * challenged.len() as Weight
+        }
+
+        /// The leaf of `dataset` that `seed` picks.
+        fn pick_leaf(dataset: &DatasetCommitment, seed: &[u8]) -> Option<ChallengedLeaf> {
+            let mut index_bytes = [0; 8];
+            index_bytes.copy_from_slice(&seed[..8]);
+            let leaf_index =
+                (u64::from_le_bytes(index_bytes) % u64::from(dataset.leaf_count?.max(1))) as u32;
+            let (subtree, depth, subtree_index) = dataset.subtree_of(leaf_index)?;
+
+            Some(ChallengedLeaf {
+                leaf_index,
+                subtree,
+                depth,
+                subtree_index,
+            })
+        }
+
+        /// Excludes identities whose challenges expire at `block_number` from the current
+        /// minting period.
+        fn expire_challenges(block_number: BlockNumberFor<T>) -> Weight {
+            let expiring = ChallengeExpiries::<T>::take(block_number);
+            let period = CurrentMintingPeriod::<T>::get();
+
+            for (account, id) in &expiring {
+                // Answered challenges are gone, and a later challenge has its own expiry.
+                let expired = DataChallenges::<T>::get(account, id)
+                    .map_or(false, |challenge| challenge.expires_at == block_number);
+                if !expired {
+                    continue;
+                }
+
+                DataChallenges::<T>::remove(account, id);
+                FailedChallenges::<T>::insert(id, period);
+                Self::remove_pending_reward(*id);
+
+                Self::deposit_event(Event::ChallengeFailed {
+                    account: account.clone(),
+                    fractal_id: *id,
+                    period,
+                });
+            }
+
+            T::DbWeight::get().reads_writes(2, 1)
+                + T::DbWeight::get().reads_writes(4, 6) * expiring.len() as Weight
+        }
+
+        /// Pays at most `max_payouts` registrants of `ActivePayouts` from the holding account.
+        fn pay_out(max_payouts: u32) -> Weight {
+            let mut budget = max_payouts;
+            let mut weight = T::DbWeight::get().reads(1);
+
+            while budget > 0 {
+                let (period, mut payout) = match ActivePayouts::<T>::iter().next() {
+                    Some(next) => next,
+                    None => break,
+                };
+
+                let mut paid = 0;
+                let mut released: u32 = 0;
+                for (id, account) in
+                    NextMintingRewards::<T>::drain_prefix(period).take(budget as usize)
+                {
+                    paid += 1;
+                    let units = Self::reward_units(NextMintingGrowth::<T>::take(period, id));
+                    released = released.saturating_add(units);
+
+                    // Revoked and expired identities forfeit their share to the next period.
+                    if Self::ensure_identity_active(id).is_err() {
+                        continue;
+                    }
+
+                    // A failed transfer leaves the reward in the holding account for the next
+                    // period, same as a deposit below the existential deposit would.
+                    let amount = payout.per_unit * units.into();
+                    let transferred = T::Currency::transfer(
+                        &T::HoldingAccount::get(),
+                        &account,
+                        amount,
+                        ExistenceRequirement::AllowDeath,
+                    );
+                    if transferred.is_ok() {
+                        Self::deposit_event(Event::PaidOut {
+                            account,
+                            fractal_id: id,
+                            period,
+                            amount,
+                        });
+                    }
+                }
+
+                payout.remaining = payout.remaining.saturating_sub(released);
+                if paid < budget {
+                    // The period is drained, release anything still promised to it.
+                    released = released.saturating_add(payout.remaining);
+                    ActivePayouts::<T>::remove(period);
+                } else {
+                    ActivePayouts::<T>::insert(period, &payout);
+                }
+                OutstandingPayouts::<T>::mutate(|o| {
+                    *o = o.saturating_sub(payout.per_unit * released.into())
+                });
+
+                budget -= paid;
+                weight = weight.saturating_add(
+                    T::DbWeight::get().reads_writes(2, 2)
+                        + T::DbWeight::get().reads_writes(5, 4) * paid as Weight,
+                );
+            }
+
+            weight
+        }
     }
 }
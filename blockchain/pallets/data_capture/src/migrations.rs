@@ -0,0 +1,131 @@
+use crate::pallet::{
+    AccountIdDatasets, Config, CurrentMintingPeriod, DatasetCommitment, FractalId,
+    NextMintingRewardUnits, NextMintingRewards, NextMintingRewardsCount, Pallet, Releases,
+    StorageVersion,
+};
+use blake2::Blake2b;
+use frame_support::{
+    inherent::Vec,
+    storage::migration::{remove_storage_prefix, storage_key_iter},
+    traits::{Get, PalletInfo},
+    weights::Weight,
+    Blake2_128Concat,
+};
+use merklex::MerkleTree;
+use sp_core::H512;
+
+pub fn migrate<T: Config>() -> Weight {
+    let mut weight = T::DbWeight::get().reads(1);
+
+    if StorageVersion::<T>::get() < Releases::V2 {
+        weight = weight.saturating_add(v2::migrate::<T>());
+        StorageVersion::<T>::put(Releases::V2);
+    }
+
+    if StorageVersion::<T>::get() < Releases::V3 {
+        weight = weight.saturating_add(v3::migrate::<T>());
+        StorageVersion::<T>::put(Releases::V3);
+    }
+
+    if StorageVersion::<T>::get() < Releases::V4 {
+        weight = weight.saturating_add(v4::migrate::<T>());
+        StorageVersion::<T>::put(Releases::V4);
+    }
+
+    weight
+}
+
+/// Moves `NextMintingRewards` from a map keyed by `FractalId` to a double map keyed by
+/// `MintingPeriod` and `FractalId`. Existing registrations belong to the current period.
+pub mod v2 {
+    use super::*;
+
+    pub fn migrate<T: Config>() -> Weight {
+        let pallet_name = <T as frame_system::Config>::PalletInfo::name::<Pallet<T>>()
+            .expect("pallet is part of the runtime");
+
+        let registrations = storage_key_iter::<FractalId, T::AccountId, Blake2_128Concat>(
+            pallet_name.as_bytes(),
+            b"NextMintingRewards",
+        )
+        .collect::<Vec<_>>();
+        remove_storage_prefix(pallet_name.as_bytes(), b"NextMintingRewards", &[]);
+
+        let period = CurrentMintingPeriod::<T>::get();
+        let count = registrations.len() as u32;
+        for (id, account) in registrations {
+            NextMintingRewards::<T>::insert(period, id, account);
+        }
+        NextMintingRewardsCount::<T>::put(count);
+
+        T::DbWeight::get().reads_writes(count as Weight + 1, 2 * count as Weight + 2)
+    }
+}
+
+/// Starts tracking reward units, and replaces the `MerkleTree`s in `AccountIdDatasets` with
+/// `DatasetCommitment`s. Every registration so far was made under `RewardMode::Even`, so it's worth
+/// a single unit.
+///
+/// Pruned subtrees can't be told apart from leaves, so the size of stored datasets is unknown.
+/// Their commitments keep the root and frontier, which registrations must still extend, and the
+/// next registration measures them afresh.
+pub mod v3 {
+    use super::*;
+
+    pub fn migrate<T: Config>() -> Weight {
+        NextMintingRewardUnits::<T>::put(NextMintingRewardsCount::<T>::get());
+
+        let mut datasets: Weight = 0;
+        AccountIdDatasets::<T>::translate::<MerkleTree<Blake2b>, _>(|_, _, dataset| {
+            datasets += 1;
+
+            Some(DatasetCommitment {
+                root: H512::from_slice(dataset.hash()),
+                leaf_count: None,
+                frontier: dataset
+                    .frontier()
+                    .into_iter()
+                    .map(H512::from_slice)
+                    .collect(),
+            })
+        });
+
+        T::DbWeight::get().reads_writes(datasets + 1, datasets + 1)
+    }
+}
+
+/// Indexes the accounts in `AccountIds` by Fractal ID, and schedules the identity expiries still to
+/// come. Staking is told about every account, so the accounts of identities already revoked or
+/// expired lose their bonus.
+///
+/// `MaxAccountsPerIdentity` and `MaxExpiriesPerBlock` aren't enforced on what's already stored.
+pub mod v4 {
+    use super::*;
+    use crate::pallet::{AccountIds, ExpiringIdentities, IdentityAccounts, IdentityExpiries};
+    use fractal_staking::OnVerificationChanged;
+
+    pub fn migrate<T: Config>() -> Weight {
+        let mut accounts: Weight = 0;
+        let mut bonuses: Weight = 0;
+        for (account, id, ()) in AccountIds::<T>::iter() {
+            accounts += 1;
+
+            IdentityAccounts::<T>::append(id, &account);
+            bonuses = bonuses.saturating_add(T::Staking::on_verification_changed(&account));
+        }
+
+        let now = <frame_system::Pallet<T>>::block_number();
+        let mut expiries: Weight = 0;
+        for (id, expires_at) in IdentityExpiries::<T>::iter() {
+            expiries += 1;
+
+            if expires_at > now {
+                ExpiringIdentities::<T>::append(expires_at, id);
+            }
+        }
+
+        T::DbWeight::get()
+            .reads_writes(accounts + expiries, accounts + expiries)
+            .saturating_add(bonuses)
+    }
+}
@@ -1,10 +1,10 @@
 use crate as fractal_data_capture;
 use frame_support::parameter_types;
 use frame_system as system;
-use sp_core::H256;
+use sp_core::{sr25519, Pair, H256};
 use sp_runtime::{
     testing::Header,
-    traits::{BlakeTwo256, IdentityLookup},
+    traits::{BlakeTwo256, Convert, Hash, IdentityLookup},
     BuildStorage,
 };
 
@@ -20,8 +20,10 @@ frame_support::construct_runtime!(
     {
         System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
         Balances: pallet_balances::{Pallet, Call, Storage, Event<T>},
+        TransactionPayment: pallet_transaction_payment::{Pallet, Storage},
         FractalMinting: fractal_data_capture::{Pallet, Call, Storage, Config<T>, Event<T>},
         FractalTokenDistribution: fractal_token_distribution::{Pallet, Call, Storage, Event<T>},
+        FractalStaking: fractal_staking::{Pallet, Call, Storage, Event<T>},
     }
 );
 
@@ -70,13 +72,31 @@ impl pallet_balances::Config for Test {
     type WeightInfo = ();
 }
 
+parameter_types! {
+    pub const TransactionByteFee: u64 = 1;
+}
+
+impl pallet_transaction_payment::Config for Test {
+    type OnChargeTransaction = pallet_transaction_payment::CurrencyAdapter<Balances, ()>;
+    type TransactionByteFee = TransactionByteFee;
+    type WeightToFee = frame_support::weights::IdentityFee<u64>;
+    type FeeMultiplierUpdate = ();
+}
+
 parameter_types! {
     pub const MintEveryNBlocks: u64 = 10;
 
     pub const TotalIssuance: u64 = 420_000_000;
     pub const IssuanceHalfLife: u64 = 600;
     pub const IssuanceCompleteAt: u64 = 10_000;
+    pub const MaxBurnTargetLength: u32 = 64;
     pub const MaxRewardPerUser: u64 = 420_000;
+    pub const MaxPayoutsPerBlock: u32 = 5;
+    pub const MaxGrowthWeightPerUser: u32 = 4;
+    pub const ChallengeResponseBlocks: u64 = 5;
+    pub const MaxChallengesPerBlock: u32 = 2;
+    pub const MaxAccountsPerIdentity: u32 = 3;
+    pub const MaxExpiriesPerBlock: u32 = 2;
     pub const HoldingAccount: u64 = 42;
 }
 
@@ -85,6 +105,16 @@ impl fractal_data_capture::Config for Test {
 
     type MintEveryNBlocks = MintEveryNBlocks;
     type MaxRewardPerUser = MaxRewardPerUser;
+    type MaxPayoutsPerBlock = MaxPayoutsPerBlock;
+    type MaxGrowthWeightPerUser = MaxGrowthWeightPerUser;
+
+    type Randomness = TestRandomness;
+    type ChallengeResponseBlocks = ChallengeResponseBlocks;
+    type MaxChallengesPerBlock = MaxChallengesPerBlock;
+
+    type Staking = FractalStaking;
+    type MaxAccountsPerIdentity = MaxAccountsPerIdentity;
+    type MaxExpiriesPerBlock = MaxExpiriesPerBlock;
 
     type HoldingAccount = HoldingAccount;
 }
@@ -95,12 +125,65 @@ impl fractal_token_distribution::Config for Test {
     type TotalIssuance = TotalIssuance;
     type IssuanceHalfLife = IssuanceHalfLife;
     type IssuanceCompleteAt = IssuanceCompleteAt;
+    type MaxBurnTargetLength = MaxBurnTargetLength;
+    type PurposeAccounts = PurposeAccounts;
+}
+
+pub struct PurposeAccounts;
+
+impl Convert<fractal_token_distribution::Purpose, u64> for PurposeAccounts {
+    fn convert(purpose: fractal_token_distribution::Purpose) -> u64 {
+        match purpose {
+            fractal_token_distribution::Purpose::DataCaptureRewards => HoldingAccount::get(),
+            fractal_token_distribution::Purpose::StakingRewards => DistributionSource::get(),
+        }
+    }
+}
+
+parameter_types! {
+    pub const DistributeEveryNBlocks: u64 = 10;
+    pub const DistributionSource: u64 = 41;
+    pub const MaxMaturingPerBlock: u32 = 16;
+    pub const MaxStakesPerAccount: u32 = 16;
+}
+
+impl fractal_staking::Config for Test {
+    type Event = Event;
+
+    type Currency = Balances;
+
+    type DistributeEveryNBlocks = DistributeEveryNBlocks;
+
+    type DistributionSource = DistributionSource;
+
+    type EarlyUnstakePenaltyHandler = fractal_staking::Redistribute<Test>;
+
+    type Verification = FractalMinting;
+
+    type MaxMaturingPerBlock = MaxMaturingPerBlock;
+
+    type StakeLocks = FractalMinting;
+
+    type MaxStakesPerAccount = MaxStakesPerAccount;
+}
+
+pub struct TestRandomness;
+
+impl frame_support::traits::Randomness<H256, u64> for TestRandomness {
+    fn random(subject: &[u8]) -> (H256, u64) {
+        (BlakeTwo256::hash(subject), 0)
+    }
+}
+
+pub fn fractal_key() -> sr25519::Pair {
+    sr25519::Pair::from_seed(&[42; 32])
 }
 
 pub fn new_test_ext() -> sp_io::TestExternalities {
     GenesisConfig {
         fractal_data_capture: crate::GenesisConfig {
             fractal_authoritative_account: 123,
+            fractal_public_keys: vec![fractal_key().public()],
         },
         ..Default::default()
     }
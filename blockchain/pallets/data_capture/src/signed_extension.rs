@@ -0,0 +1,105 @@
+use crate::pallet::{
+    Call, Config, CurrentMintingPeriod, Error, FractalAuthoritativeAccount, NextMintingRewards,
+    Pallet,
+};
+use codec::{Decode, Encode};
+use core::marker::PhantomData;
+use frame_support::{dispatch::DispatchError, traits::IsSubType};
+use sp_runtime::{
+    traits::{DispatchInfoOf, SignedExtension},
+    transaction_validity::{
+        InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+    },
+};
+
+/// Rejects free data capture calls that would fail, so they can't be used to fill blocks for
+/// nothing. Registering for minting is also limited to once per Fractal ID per minting period, and
+/// registering an account to a Fractal ID to a single transaction in the pool.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct CheckDataCapture<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckDataCapture<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Config + Send + Sync> Default for CheckDataCapture<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config + Send + Sync> core::fmt::Debug for CheckDataCapture<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "CheckDataCapture")
+    }
+}
+
+fn invalid(error: DispatchError) -> TransactionValidityError {
+    match error {
+        DispatchError::Module { error, .. } => InvalidTransaction::Custom(error).into(),
+        _ => InvalidTransaction::Call.into(),
+    }
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckDataCapture<T>
+where
+    <T as frame_system::Config>::Call: IsSubType<Call<T>>,
+{
+    const IDENTIFIER: &'static str = "CheckDataCapture";
+    type AccountId = T::AccountId;
+    type Call = <T as frame_system::Config>::Call;
+    type AdditionalSigned = ();
+    type Pre = ();
+
+    fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> TransactionValidity {
+        match call.is_sub_type() {
+            Some(Call::register_identity(..)) => {
+                if who != &FractalAuthoritativeAccount::<T>::get() {
+                    return Err(invalid(Error::<T>::MustBeFractal.into()));
+                }
+
+                Ok(ValidTransaction::default())
+            }
+            Some(Call::register_identity_signed(attestation, signer, signature)) => {
+                Pallet::<T>::validate_attestation(who, attestation, signer, signature)
+                    .map_err(invalid)?;
+
+                Ok(ValidTransaction {
+                    provides: core::iter::once(
+                        (Self::IDENTIFIER, who, attestation.fractal_id).encode(),
+                    )
+                    .collect(),
+                    ..Default::default()
+                })
+            }
+            Some(Call::register_for_minting(identity, extension_proof)) => {
+                let (id, _, _) =
+                    Pallet::<T>::validate_registration(who, *identity, extension_proof)
+                        .map_err(invalid)?;
+
+                let period = CurrentMintingPeriod::<T>::get();
+                if NextMintingRewards::<T>::contains_key(period, id) {
+                    return Err(invalid(Error::<T>::AlreadyRegisteredThisPeriod.into()));
+                }
+
+                Ok(ValidTransaction {
+                    provides: core::iter::once((Self::IDENTIFIER, id, period).encode()).collect(),
+                    ..Default::default()
+                })
+            }
+            _ => Ok(ValidTransaction::default()),
+        }
+    }
+}
@@ -250,6 +250,43 @@ mod register_identity {
         });
     }
 
+    #[test]
+    fn fee_is_refunded_for_first_registration_only() {
+        use frame_support::weights::GetDispatchInfo;
+        use pallet_transaction_payment::ChargeTransactionPayment;
+        use sp_runtime::traits::{Dispatchable, SignedExtension};
+
+        fn register_paying_fee(account: u64, dataset: &[&str]) {
+            let call = mock::Call::FractalMinting(crate::Call::register_for_minting(
+                None,
+                MerkleTree::from_iter(dataset).unwrap(),
+            ));
+            let info = call.get_dispatch_info();
+            let pre = ChargeTransactionPayment::<Test>::from(0)
+                .pre_dispatch(&account, &call, &info, 0)
+                .unwrap();
+            let post = call.dispatch(Origin::signed(account)).unwrap();
+            assert_ok!(ChargeTransactionPayment::<Test>::post_dispatch(
+                pre,
+                &info,
+                &post,
+                0,
+                &Ok(())
+            ));
+        }
+
+        run_test(|| {
+            register_id_account(42, 1);
+            Balances::make_free_balance_be(&1, 1_000_000_000_000);
+
+            register_paying_fee(1, &["a", "b"]);
+            assert_eq!(Balances::free_balance(&1), 1_000_000_000_000);
+
+            register_paying_fee(1, &["a", "b", "c"]);
+            assert!(Balances::free_balance(&1) < 1_000_000_000_000);
+        });
+    }
+
     #[test]
     fn second_call_to_register_for_minting_is_paid() {
         run_test(|| {
@@ -308,60 +345,2223 @@ mod register_identity {
     }
 
     #[cfg(test)]
-    mod extension_proofs {
+    mod multi_block_payout {
         use super::*;
 
+        fn max_payouts_per_block() -> u64 {
+            <Test as crate::Config>::MaxPayoutsPerBlock::get() as u64
+        }
+
+        fn paid_accounts(users: u64) -> u64 {
+            (1..=users)
+                .filter(|&id| Balances::free_balance(&id) > 0)
+                .count() as u64
+        }
+
         #[test]
-        fn second_proof_does_not_extend_initial_proof() {
+        fn spreads_payout_across_blocks() {
+            run_test(|| {
+                let users = 2 * max_payouts_per_block() + 2;
+                for id in 1..=users {
+                    register_id_account(id, id);
+                    register_for_minting(id);
+                }
+
+                run_to_next_minting();
+                assert_eq!(paid_accounts(users), max_payouts_per_block());
+
+                step_block();
+                assert_eq!(paid_accounts(users), 2 * max_payouts_per_block());
+
+                step_block();
+                assert_eq!(paid_accounts(users), users);
+                for id in 1..=users {
+                    assert_eq!(Balances::free_balance(&id), FIRST_MINTING_TOTAL / users);
+                }
+            });
+        }
+
+        #[test]
+        fn registration_during_payout_waits_for_next_period() {
+            run_test(|| {
+                let users = 2 * max_payouts_per_block();
+                for id in 1..=users {
+                    register_id_account(id, id);
+                    register_for_minting(id);
+                }
+                run_to_next_minting();
+
+                let late = users + 1;
+                register_id_account(late, late);
+                register_for_minting(late);
+                step_block();
+
+                assert_eq!(paid_accounts(users), users);
+                assert_eq!(Balances::free_balance(&late), 0);
+
+                Balances::make_free_balance_be(
+                    &<Test as crate::Config>::HoldingAccount::get(),
+                    FIRST_MINTING_TOTAL,
+                );
+                run_to_next_minting();
+                assert!(Balances::free_balance(&late) > 0);
+            });
+        }
+
+        #[test]
+        fn promised_rewards_are_not_minted_twice() {
+            run_test(|| {
+                let users = 2 * max_payouts_per_block();
+                for id in 1..=users {
+                    register_id_account(id, id);
+                    register_for_minting(id);
+                }
+                run_to_next_minting();
+
+                assert_eq!(
+                    crate::OutstandingPayouts::<Test>::get(),
+                    FIRST_MINTING_TOTAL / users * max_payouts_per_block()
+                );
+
+                step_block();
+                assert_eq!(crate::OutstandingPayouts::<Test>::get(), 0);
+                assert_eq!(
+                    Balances::free_balance(&<Test as crate::Config>::HoldingAccount::get()),
+                    FIRST_MINTING_TOTAL % users
+                );
+            });
+        }
+
+        #[test]
+        fn revoked_identity_is_skipped_mid_payout() {
+            run_test(|| {
+                let users = 2 * max_payouts_per_block();
+                for id in 1..=users {
+                    register_id_account(id, id);
+                    register_for_minting(id);
+                }
+                run_to_next_minting();
+
+                let unpaid = (1..=users)
+                    .find(|&id| Balances::free_balance(&id) == 0)
+                    .unwrap();
+                assert_ok!(FractalMinting::revoke_identity(Origin::root(), unpaid));
+                step_block();
+
+                assert_eq!(Balances::free_balance(&unpaid), 0);
+                assert_eq!(paid_accounts(users), users - 1);
+                assert_eq!(crate::OutstandingPayouts::<Test>::get(), 0);
+            });
+        }
+
+        #[test]
+        fn expired_identity_is_skipped_mid_payout() {
+            run_test(|| {
+                let users = 2 * max_payouts_per_block();
+                for id in 1..=users {
+                    register_id_account(id, id);
+                    register_for_minting(id);
+                }
+                run_to_next_minting();
+
+                let unpaid = (1..=users)
+                    .find(|&id| Balances::free_balance(&id) == 0)
+                    .unwrap();
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::root(),
+                    unpaid,
+                    Some(System::block_number() + 1)
+                ));
+                step_block();
+
+                assert_eq!(Balances::free_balance(&unpaid), 0);
+                assert_eq!(paid_accounts(users), users - 1);
+                assert_eq!(crate::OutstandingPayouts::<Test>::get(), 0);
+            });
+        }
+
+        #[test]
+        fn migrates_registrations_to_current_period() {
+            new_test_ext().execute_with(|| {
+                use codec::Encode;
+                use frame_support::{storage::migration::put_storage_value, StorageHasher};
+
+                crate::StorageVersion::<Test>::put(crate::Releases::V1);
+                for (id, account) in &[(42u64, 1u64), (43, 2)] {
+                    put_storage_value(
+                        b"FractalMinting",
+                        b"NextMintingRewards",
+                        &frame_support::Blake2_128Concat::hash(&id.encode()),
+                        account,
+                    );
+                }
+
+                crate::migrations::migrate::<Test>();
+
+                assert_eq!(crate::NextMintingRewards::<Test>::get(0, 42), Some(1));
+                assert_eq!(crate::NextMintingRewards::<Test>::get(0, 43), Some(2));
+                assert_eq!(crate::NextMintingRewardsCount::<Test>::get(), 2);
+                assert_eq!(crate::StorageVersion::<Test>::get(), crate::Releases::V4);
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod growth_rewards {
+        use super::*;
+
+        fn per_unit() -> u64 {
+            max_reward_per_user() / <Test as crate::Config>::MaxGrowthWeightPerUser::get() as u64
+        }
+
+        fn dataset(leaves: u32) -> MerkleTree<Blake2b> {
+            MerkleTree::from_iter((0..leaves).map(|i| i.to_string())).unwrap()
+        }
+
+        fn register_dataset(account: u64, leaves: u32) {
+            register_proof(account, dataset(leaves));
+        }
+
+        fn register_proof(account: u64, proof: MerkleTree<Blake2b>) {
+            assert_ok!(FractalMinting::register_for_minting(
+                Origin::signed(account),
+                None,
+                proof,
+            ));
+        }
+
+        /// Registers a single leaf dataset for each of `accounts`, so that their next
+        /// registrations are measured from it, and starts a new period with a full holding
+        /// account.
+        fn register_first_datasets(accounts: core::ops::RangeInclusive<u64>) {
+            for account in accounts {
+                register_id_account(account, account);
+                register_dataset(account, 1);
+            }
+
+            run_to_next_minting();
+            Balances::make_free_balance_be(
+                &<Test as crate::Config>::HoldingAccount::get(),
+                FIRST_MINTING_TOTAL,
+            );
+        }
+
+        /// Runs to the next minting, returning what `account` was paid.
+        fn paid_next_minting(account: u64) -> u64 {
+            let before = Balances::free_balance(&account);
+            run_to_next_minting();
+            Balances::free_balance(&account) - before
+        }
+
+        fn run_growth_test(f: impl FnOnce()) {
+            run_test(|| {
+                assert_ok!(FractalMinting::set_reward_mode(
+                    Origin::root(),
+                    RewardMode::DatasetGrowth
+                ));
+                f();
+            });
+        }
+
+        #[test]
+        fn weights_reward_by_added_leaves() {
+            run_growth_test(|| {
+                register_first_datasets(1..=2);
+                register_dataset(1, 17);
+                register_dataset(2, 5);
+
+                let before = Balances::free_balance(&2);
+                assert_eq!(paid_next_minting(1), 4 * per_unit());
+                assert_eq!(Balances::free_balance(&2) - before, 2 * per_unit());
+            });
+        }
+
+        #[test]
+        fn caps_weight_per_user() {
+            run_growth_test(|| {
+                register_first_datasets(1..=1);
+                register_dataset(1, 101);
+
+                assert_eq!(paid_next_minting(1), max_reward_per_user());
+            });
+        }
+
+        #[test]
+        fn shares_holding_account_by_weight() {
+            run_growth_test(|| {
+                register_first_datasets(1..=5);
+                for id in 1..=4 {
+                    register_dataset(id, 17);
+                }
+                register_dataset(5, 2);
+
+                let before = Balances::free_balance(&5);
+                let per_unit = FIRST_MINTING_TOTAL / 17;
+                assert_eq!(paid_next_minting(1), 4 * per_unit);
+                assert_eq!(Balances::free_balance(&5) - before, per_unit);
+            });
+        }
+
+        #[test]
+        fn only_new_leaves_count() {
+            run_growth_test(|| {
+                register_first_datasets(1..=1);
+                register_dataset(1, 16);
+                run_to_next_minting();
+
+                Balances::make_free_balance_be(
+                    &<Test as crate::Config>::HoldingAccount::get(),
+                    FIRST_MINTING_TOTAL,
+                );
+                register_dataset(1, 17);
+
+                assert_eq!(paid_next_minting(1), per_unit());
+            });
+        }
+
+        #[test]
+        fn pruned_leaves_count_once() {
+            run_growth_test(|| {
+                register_first_datasets(1..=1);
+                register_proof(
+                    1,
+                    MerkleTree::merge(dataset(1), dataset(16).prune_balanced()),
+                );
+
+                assert_eq!(paid_next_minting(1), per_unit());
+            });
+        }
+
+        #[test]
+        fn pruned_leaves_must_fit_dataset() {
+            run_growth_test(|| {
+                register_first_datasets(1..=1);
+
+                assert_noop!(
+                    FractalMinting::register_for_minting(
+                        Origin::signed(1),
+                        None,
+                        dataset(17).extension_proof(&dataset(1)).unwrap()
+                    ),
+                    Error::<Test>::NewLeavesNotRevealed
+                );
+            });
+        }
+
+        #[test]
+        fn first_registration_adds_no_growth() {
+            run_growth_test(|| {
+                register_id_account(1, 1);
+                register_dataset(1, 100);
+
+                assert_eq!(paid_next_minting(1), per_unit());
+            });
+        }
+
+        #[test]
+        fn repeat_registration_replaces_growth() {
+            run_growth_test(|| {
+                register_first_datasets(1..=1);
+                register_dataset(1, 17);
+                register_dataset(1, 18);
+
+                assert_eq!(paid_next_minting(1), per_unit());
+            });
+        }
+
+        #[test]
+        fn even_mode_ignores_growth() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_id_account(2, 2);
+                register_dataset(1, 1);
+                register_dataset(2, 1);
+                run_to_next_minting();
+
+                register_dataset(1, 17);
+                register_dataset(2, 2);
+                run_to_next_minting();
+
+                assert_eq!(Balances::free_balance(&1), Balances::free_balance(&2));
+            });
+        }
+
+        #[test]
+        fn reward_mode_requires_root() {
+            run_test(|| {
+                assert_noop!(
+                    FractalMinting::set_reward_mode(
+                        Origin::signed(123),
+                        RewardMode::DatasetGrowth
+                    ),
+                    sp_runtime::DispatchError::BadOrigin
+                );
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod dataset_commitments {
+        use super::*;
+        use codec::Encode;
+        use frame_support::{
+            storage::migration::put_storage_value, Blake2_128Concat, StorageHasher,
+        };
+
+        /// What the v3 migration stores for `tree`.
+        fn migrated(tree: &MerkleTree<Blake2b>) -> DatasetCommitment {
+            DatasetCommitment {
+                root: sp_core::H512::from_slice(tree.hash()),
+                leaf_count: None,
+                frontier: tree
+                    .frontier()
+                    .into_iter()
+                    .map(sp_core::H512::from_slice)
+                    .collect(),
+            }
+        }
+
+        fn stored_leaf_count(account: u64) -> Option<u32> {
+            crate::AccountIdDatasets::<Test>::get(account, account)?.leaf_count
+        }
+
+        #[test]
+        fn stores_frontier_of_dataset() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_for_minting_dataset(1, &["a", "b", "c", "d", "e", "f", "g"]);
+
+                let stored = crate::AccountIdDatasets::<Test>::get(1, 1).unwrap();
+                assert_eq!(stored.frontier.len(), 3);
+                assert_eq!(stored.leaf_count, Some(7));
+            });
+        }
+
+        #[test]
+        fn stores_leaf_count_of_pruned_dataset() {
             run_test(|| {
                 register_id_account(1, 1);
+                register_for_minting_dataset(1, &["a", "b", "c", "d"]);
                 assert_ok!(FractalMinting::register_for_minting(
                     Origin::signed(1),
                     None,
-                    simple_tree().prune_balanced()
+                    MerkleTree::from_iter(&["a", "b", "c", "d", "e"])
+                        .unwrap()
+                        .prune_balanced(),
                 ));
+
+                let stored = crate::AccountIdDatasets::<Test>::get(1, 1).unwrap();
+                assert_eq!(stored.leaf_count, Some(5));
+            });
+        }
+
+        #[test]
+        fn new_leaves_must_be_revealed() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_for_minting_dataset(1, &["a"]);
+
+                let full = MerkleTree::<Blake2b>::from_iter(&["a", "b", "c", "d", "e"]).unwrap();
+                let base = MerkleTree::<Blake2b>::from_iter(&["a"]).unwrap();
                 assert_noop!(
                     FractalMinting::register_for_minting(
                         Origin::signed(1),
                         None,
-                        simple_tree().prune_balanced()
+                        full.extension_proof(&base).unwrap(),
                     ),
-                    Error::<Test>::ExtensionDoesNotExtendExistingDataset
+                    Error::<Test>::NewLeavesNotRevealed
                 );
+                assert_ok!(FractalMinting::register_for_minting(
+                    Origin::signed(1),
+                    None,
+                    full
+                ));
             });
         }
 
         #[test]
-        fn multiple_identities_requires_specifying_identity() {
+        fn extends_pruned_commitment() {
             run_test(|| {
-                register_id_account(42, 1);
-                register_id_account(43, 1);
+                register_id_account(1, 1);
+                let submitted = MerkleTree::<Blake2b>::from_iter(&["a", "b", "c"])
+                    .unwrap()
+                    .prune_balanced();
+                assert_ok!(FractalMinting::register_for_minting(
+                    Origin::signed(1),
+                    None,
+                    submitted.clone(),
+                ));
+                assert_eq!(stored_leaf_count(1), Some(2));
 
+                assert_ok!(FractalMinting::register_for_minting(
+                    Origin::signed(1),
+                    None,
+                    submitted.push("d").push("e"),
+                ));
+                assert_eq!(stored_leaf_count(1), Some(4));
                 assert_noop!(
                     FractalMinting::register_for_minting(
                         Origin::signed(1),
                         None,
-                        simple_tree().prune_balanced()
+                        MerkleTree::from_iter(&["a", "b", "x", "d", "e", "f"]).unwrap(),
                     ),
-                    Error::<Test>::MustSpecifyFractalIdWithMultipleIds
+                    Error::<Test>::ExtensionDoesNotExtendExistingDataset
                 );
             });
         }
 
         #[test]
-        fn provided_identity_not_registered() {
+        fn migrates_trees_to_commitments() {
+            new_test_ext().execute_with(|| {
+                crate::StorageVersion::<Test>::put(crate::Releases::V2);
+
+                let tree = MerkleTree::<Blake2b>::from_iter(&["a", "b", "c"]).unwrap();
+                let mut key = Blake2_128Concat::hash(&1u64.encode());
+                key.extend(Blake2_128Concat::hash(&42u64.encode()));
+                put_storage_value(b"FractalMinting", b"AccountIdDatasets", &key, &tree);
+
+                crate::migrations::migrate::<Test>();
+
+                assert_eq!(
+                    crate::AccountIdDatasets::<Test>::get(1, 42),
+                    Some(migrated(&tree))
+                );
+                assert_eq!(crate::StorageVersion::<Test>::get(), crate::Releases::V4);
+            });
+        }
+
+        #[test]
+        fn measures_migrated_dataset_afresh() {
             run_test(|| {
-                register_id_account(42, 1);
+                register_id_account(1, 1);
+                let tree = MerkleTree::<Blake2b>::from_iter(&["a", "b", "c"]).unwrap();
+                crate::AccountIdDatasets::<Test>::insert(1, 1, migrated(&tree));
 
                 assert_noop!(
-                    FractalMinting::register_for_minting(
-                        Origin::signed(1),
-                        Some(43),
-                        simple_tree().prune_balanced()
-                    ),
-                    Error::<Test>::FractalIdNotRegisteredToAccount
+                    FractalMinting::register_for_minting(Origin::signed(1), None, tree),
+                    Error::<Test>::ExtensionDoesNotExtendExistingDataset
+                );
+                register_for_minting_dataset(1, &["a", "b", "c", "d"]);
+                assert_eq!(stored_leaf_count(1), Some(4));
+            });
+        }
+
+        #[test]
+        fn migrated_dataset_cannot_be_challenged() {
+            run_test(|| {
+                register_id_account(1, 1);
+                let tree = MerkleTree::<Blake2b>::from_iter(&["a", "b", "c"]).unwrap();
+                crate::AccountIdDatasets::<Test>::insert(1, 1, migrated(&tree));
+
+                assert_noop!(
+                    FractalMinting::challenge_dataset(Origin::signed(99), 1, 1),
+                    Error::<Test>::UnknownLeafCount
                 );
             });
         }
     }
+
+    #[cfg(test)]
+    mod data_challenges {
+        use super::*;
+
+        const ITEMS: &[&str] = &["a", "b", "c", "d"];
+
+        fn challenge(account: u64) {
+            assert_ok!(FractalMinting::challenge_dataset(
+                Origin::signed(99),
+                account,
+                account
+            ));
+        }
+
+        /// Runs to the block in which the leaves of challenges made in this block are picked.
+        fn run_to_pick() {
+            for _ in 0..CHALLENGE_PICK_DELAY {
+                step_block();
+            }
+        }
+
+        fn challenged_leaf(account: u64) -> ChallengedLeaf {
+            crate::DataChallenges::<Test>::get(account, account)
+                .expect("challenge is open")
+                .leaf
+                .expect("leaf is picked")
+        }
+
+        fn respond(
+            account: u64,
+            proof: MerkleTree<Blake2b>,
+            data: &str,
+        ) -> sp_runtime::DispatchResult {
+            FractalMinting::respond_to_challenge(
+                Origin::signed(account),
+                account,
+                proof,
+                data.as_bytes().to_vec(),
+            )
+        }
+
+        fn register_with_items(account: u64) {
+            register_id_account(account, account);
+            register_for_minting_dataset(account, ITEMS);
+        }
+
+        fn find_subtree(tree: &MerkleTree<Blake2b>, hash: &[u8]) -> Option<MerkleTree<Blake2b>> {
+            if tree.hash().as_slice() == hash {
+                return Some(tree.clone());
+            }
+
+            let (left, right) = tree.children()?;
+            find_subtree(left, hash).or_else(|| find_subtree(right, hash))
+        }
+
+        /// The proof and data that answer the open challenge against a dataset of `items`.
+        fn answer_for(account: u64, items: &[&'static str]) -> (MerkleTree<Blake2b>, &'static str) {
+            let leaf = challenged_leaf(account);
+            let tree = MerkleTree::<Blake2b>::from_iter(items).unwrap();
+            let proof = find_subtree(&tree, leaf.subtree.as_bytes()).unwrap();
+
+            (proof, items[leaf.leaf_index as usize])
+        }
+
+        fn answer(account: u64) -> (MerkleTree<Blake2b>, &'static str) {
+            answer_for(account, ITEMS)
+        }
+
+        #[test]
+        fn owner_can_answer_challenge() {
+            run_test(|| {
+                register_with_items(1);
+                challenge(1);
+                run_to_pick();
+
+                let (proof, data) = answer(1);
+                assert_ok!(respond(1, proof, data));
+                assert_eq!(crate::DataChallenges::<Test>::get(1, 1), None);
+
+                run_to_next_minting();
+
+                assert_eq!(Balances::free_balance(&1), max_reward_per_user());
+            });
+        }
+
+        #[test]
+        fn challenges_every_leaf() {
+            run_test(|| {
+                const ITEMS: &[&str] = &["a", "b", "c", "d", "e"];
+                register_id_account(1, 1);
+                register_for_minting_dataset(1, ITEMS);
+
+                let mut challenged = [false; 5];
+                for _ in 0..50 {
+                    challenge(1);
+                    run_to_pick();
+                    challenged[challenged_leaf(1).leaf_index as usize] = true;
+
+                    let (proof, data) = answer_for(1, ITEMS);
+                    assert_ok!(respond(1, proof, data));
+                    step_block();
+                }
+
+                assert_eq!(challenged, [true; 5]);
+            });
+        }
+
+        #[test]
+        fn rejects_wrong_data() {
+            run_test(|| {
+                register_with_items(1);
+                challenge(1);
+                run_to_pick();
+
+                assert_noop!(
+                    respond(1, MerkleTree::leaf_bytes("e"), "e"),
+                    Error::<Test>::InvalidChallengeResponse
+                );
+            });
+        }
+
+        #[test]
+        fn pruned_leaf_must_be_opened() {
+            run_test(|| {
+                register_with_items(1);
+                let tree = MerkleTree::<Blake2b>::from_iter(ITEMS).unwrap();
+                challenge(1);
+                run_to_pick();
+
+                let data = ITEMS[challenged_leaf(1).leaf_index as usize];
+
+                assert_noop!(
+                    respond(1, tree.clone().prune_balanced(), data),
+                    Error::<Test>::InvalidChallengeResponse
+                );
+                assert_ok!(respond(1, tree, data));
+            });
+        }
+
+        #[test]
+        fn children_hashes_are_not_leaf_data() {
+            run_test(|| {
+                register_with_items(1);
+                challenge(1);
+                run_to_pick();
+
+                let tree = MerkleTree::<Blake2b>::from_iter(ITEMS).unwrap();
+                let (left, right) = tree.children().unwrap();
+                let data = [left.hash().as_slice(), right.hash().as_slice()].concat();
+
+                assert_noop!(
+                    FractalMinting::respond_to_challenge(
+                        Origin::signed(1),
+                        1,
+                        MerkleTree::leaf(tree.hash().clone()),
+                        data,
+                    ),
+                    Error::<Test>::InvalidChallengeResponse
+                );
+            });
+        }
+
+        #[test]
+        fn unanswered_challenge_excludes_from_current_period() {
+            run_test(|| {
+                register_with_items(1);
+                challenge(1);
+                run_to_pick();
+
+                for _ in 0..=<Test as crate::Config>::ChallengeResponseBlocks::get() {
+                    step_block();
+                }
+                assert_eq!(crate::DataChallenges::<Test>::get(1, 1), None);
+                assert_noop!(
+                    FractalMinting::register_for_minting(
+                        Origin::signed(1),
+                        None,
+                        MerkleTree::from_iter(&["a", "b", "c", "d", "e"]).unwrap(),
+                    ),
+                    Error::<Test>::FailedDataChallenge
+                );
+
+                run_to_next_minting();
+                assert_eq!(Balances::free_balance(&1), 0);
+
+                assert_ok!(FractalMinting::register_for_minting(
+                    Origin::signed(1),
+                    None,
+                    MerkleTree::from_iter(&["a", "b", "c", "d", "e"]).unwrap(),
+                ));
+            });
+        }
+
+        #[test]
+        fn cannot_answer_expired_challenge() {
+            run_test(|| {
+                register_with_items(1);
+                challenge(1);
+                run_to_pick();
+                let (proof, data) = answer(1);
+
+                for _ in 0..=<Test as crate::Config>::ChallengeResponseBlocks::get() {
+                    step_block();
+                }
+
+                assert_noop!(
+                    respond(1, proof, data),
+                    Error::<Test>::NoChallengeOpen
+                );
+            });
+        }
+
+        #[test]
+        fn requires_dataset() {
+            run_test(|| {
+                register_id_account(1, 1);
+
+                assert_noop!(
+                    FractalMinting::challenge_dataset(Origin::signed(99), 1, 1),
+                    Error::<Test>::NoDatasetToChallenge
+                );
+            });
+        }
+
+        #[test]
+        fn one_challenge_at_a_time() {
+            run_test(|| {
+                register_with_items(1);
+                challenge(1);
+
+                assert_noop!(
+                    FractalMinting::challenge_dataset(Origin::signed(99), 1, 1),
+                    Error::<Test>::ChallengeOpen
+                );
+            });
+        }
+
+        #[test]
+        fn limits_challenges_per_block() {
+            run_test(|| {
+                for account in 1..=3 {
+                    register_with_items(account);
+                }
+                challenge(1);
+                challenge(2);
+
+                assert_noop!(
+                    FractalMinting::challenge_dataset(Origin::signed(99), 3, 3),
+                    Error::<Test>::TooManyChallenges
+                );
+            });
+        }
+
+        #[test]
+        fn picks_leaf_in_later_block() {
+            run_test(|| {
+                register_with_items(1);
+                challenge(1);
+
+                assert_eq!(crate::DataChallenges::<Test>::get(1, 1).unwrap().leaf, None);
+                assert_noop!(
+                    respond(1, MerkleTree::leaf_bytes("a"), "a"),
+                    Error::<Test>::ChallengedLeafNotPicked
+                );
+
+                run_to_pick();
+                let (proof, data) = answer(1);
+                assert_ok!(respond(1, proof, data));
+            });
+        }
+
+        #[test]
+        fn owner_cannot_challenge_own_dataset() {
+            run_test(|| {
+                register_with_items(1);
+                register_id_account(1, 2);
+
+                for owner in 1..=2 {
+                    assert_noop!(
+                        FractalMinting::challenge_dataset(Origin::signed(owner), 1, 1),
+                        Error::<Test>::CannotChallengeOwnDataset
+                    );
+                }
+            });
+        }
+
+        #[test]
+        fn cannot_migrate_challenged_identity() {
+            run_test(|| {
+                register_with_items(1);
+                register_id_account(1, 2);
+                challenge(1);
+
+                assert_noop!(
+                    FractalMinting::migrate_identity(Origin::signed(2), 1, 1),
+                    Error::<Test>::ChallengeOpen
+                );
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod stake_requirement {
+        use super::*;
+
+        const LOCK: u64 = 100;
+        const MIN_STAKE: u64 = 1_000;
+
+        fn run_stake_test(f: impl FnOnce()) {
+            run_test(|| {
+                assert_ok!(FractalStaking::set_lock_period_shares(
+                    Origin::root(),
+                    LOCK,
+                    1
+                ));
+                assert_ok!(FractalMinting::set_stake_requirement(
+                    Origin::root(),
+                    Some(StakeRequirement {
+                        min_amount: MIN_STAKE,
+                        lock_period: LOCK,
+                    })
+                ));
+                register_id_account(1, 1);
+
+                f();
+            });
+        }
+
+        fn stake(account: u64, amount: u64) {
+            let _ = Balances::deposit_creating(&account, amount);
+            assert_ok!(FractalStaking::stake(Origin::signed(account), LOCK, amount));
+        }
+
+        #[test]
+        fn requires_stake() {
+            run_stake_test(|| {
+                assert_noop!(
+                    FractalMinting::register_for_minting(Origin::signed(1), None, simple_tree()),
+                    Error::<Test>::InsufficientStake
+                );
+            });
+        }
+
+        #[test]
+        fn registers_with_stake() {
+            run_stake_test(|| {
+                stake(1, MIN_STAKE);
+
+                register_for_minting(1);
+            });
+        }
+
+        #[test]
+        fn requires_minimum_amount() {
+            run_stake_test(|| {
+                stake(1, MIN_STAKE - 1);
+
+                assert_noop!(
+                    FractalMinting::register_for_minting(Origin::signed(1), None, simple_tree()),
+                    Error::<Test>::InsufficientStake
+                );
+            });
+        }
+
+        #[test]
+        fn requires_stake_locked_for_lock_period() {
+            run_stake_test(|| {
+                stake(1, MIN_STAKE);
+                step_block();
+
+                assert_noop!(
+                    FractalMinting::register_for_minting(Origin::signed(1), None, simple_tree()),
+                    Error::<Test>::InsufficientStake
+                );
+            });
+        }
+
+        #[test]
+        fn registered_stake_cannot_be_unstaked_early() {
+            run_stake_test(|| {
+                assert_ok!(FractalStaking::set_early_unstake_penalty(
+                    Origin::root(),
+                    Some(sp_runtime::Perbill::from_percent(10))
+                ));
+                stake(1, MIN_STAKE);
+                let unstake_at = System::block_number() + LOCK;
+                register_for_minting(1);
+
+                assert_noop!(
+                    FractalStaking::unstake_early(Origin::signed(1), 1, unstake_at),
+                    fractal_staking::Error::<Test>::StakeLocked
+                );
+            });
+        }
+
+        #[test]
+        fn stake_beyond_requirement_can_be_unstaked_early() {
+            run_stake_test(|| {
+                assert_ok!(FractalStaking::set_early_unstake_penalty(
+                    Origin::root(),
+                    Some(sp_runtime::Perbill::from_percent(10))
+                ));
+                stake(1, MIN_STAKE);
+                register_for_minting(1);
+                assert_ok!(FractalStaking::set_lock_period_shares(
+                    Origin::root(),
+                    LOCK + 1,
+                    1
+                ));
+                let _ = Balances::deposit_creating(&1, MIN_STAKE);
+                assert_ok!(FractalStaking::stake(Origin::signed(1), LOCK + 1, MIN_STAKE));
+                let unstake_at = System::block_number() + LOCK + 1;
+
+                assert_ok!(FractalStaking::unstake_early(Origin::signed(1), 1, unstake_at));
+            });
+        }
+
+        #[test]
+        fn lock_ends_after_lock_period() {
+            run_stake_test(|| {
+                let stake_lock = || {
+                    <FractalMinting as fractal_staking::StakeLocks<_, _, _>>::stake_lock(&1)
+                };
+                stake(1, MIN_STAKE);
+                register_for_minting(1);
+                assert_eq!(stake_lock(), Some((System::block_number() + LOCK, MIN_STAKE)));
+
+                for _ in 0..LOCK {
+                    step_block();
+                }
+
+                assert_eq!(stake_lock(), None);
+            });
+        }
+
+        #[test]
+        fn requirement_can_be_removed() {
+            run_stake_test(|| {
+                assert_ok!(FractalMinting::set_stake_requirement(Origin::root(), None));
+
+                register_for_minting(1);
+            });
+        }
+
+        #[test]
+        fn slashes_fraudulent_dataset() {
+            run_stake_test(|| {
+                stake(1, MIN_STAKE);
+                register_for_minting(1);
+
+                assert_ok!(FractalMinting::slash_fraudulent_dataset(
+                    Origin::root(),
+                    1,
+                    1,
+                    MIN_STAKE / 2
+                ));
+
+                assert_eq!(Balances::reserved_balance(&1), MIN_STAKE / 2);
+                assert_eq!(
+                    Balances::free_balance(&<Test as crate::Config>::HoldingAccount::get()),
+                    FIRST_MINTING_TOTAL + MIN_STAKE / 2
+                );
+                assert_eq!(crate::NextMintingRewards::<Test>::get(0, 1), None);
+
+                run_to_next_minting();
+                assert_eq!(Balances::free_balance(&1), 0);
+            });
+        }
+
+        #[test]
+        fn slashes_only_accounts_of_fractal_id() {
+            run_stake_test(|| {
+                stake(1, MIN_STAKE);
+                register_for_minting(1);
+                register_id_account(2, 2);
+
+                assert_noop!(
+                    FractalMinting::slash_fraudulent_dataset(Origin::root(), 1, 2, MIN_STAKE),
+                    Error::<Test>::FractalIdNotRegisteredToAccount
+                );
+                assert_eq!(crate::NextMintingRewards::<Test>::get(0, 1), Some(1));
+            });
+        }
+
+        #[test]
+        fn slashing_requires_root() {
+            run_stake_test(|| {
+                stake(1, MIN_STAKE);
+
+                assert_noop!(
+                    FractalMinting::slash_fraudulent_dataset(Origin::signed(123), 1, 1, MIN_STAKE),
+                    sp_runtime::DispatchError::BadOrigin
+                );
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod events {
+        use super::*;
+        use core::convert::TryInto;
+
+        fn data_capture_events() -> Vec<crate::Event<Test>> {
+            System::events()
+                .into_iter()
+                .filter_map(|record| record.event.try_into().ok())
+                .collect()
+        }
+
+        fn root(tree: &MerkleTree<Blake2b>) -> sp_core::H512 {
+            sp_core::H512::from_slice(tree.hash())
+        }
+
+        #[test]
+        fn identity_registration() {
+            run_test(|| {
+                register_id_account(1, 2);
+
+                assert_eq!(
+                    data_capture_events(),
+                    vec![crate::Event::IdentityRegistered {
+                        fractal_id: 1,
+                        account: 2
+                    }]
+                );
+            });
+        }
+
+        #[test]
+        fn dataset_extension() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_for_minting_dataset(1, &["a", "b"]);
+                register_for_minting_dataset(1, &["a", "b", "c"]);
+
+                let first = MerkleTree::<Blake2b>::from_iter(&["a", "b"]).unwrap();
+                let second = MerkleTree::<Blake2b>::from_iter(&["a", "b", "c"]).unwrap();
+                let extensions = data_capture_events()
+                    .into_iter()
+                    .filter(|event| matches!(event, crate::Event::DatasetExtended { .. }))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    extensions,
+                    vec![
+                        crate::Event::DatasetExtended {
+                            account: 1,
+                            fractal_id: 1,
+                            old_root: None,
+                            new_root: root(&first),
+                            old_leaf_count: None,
+                            new_leaf_count: Some(2),
+                        },
+                        crate::Event::DatasetExtended {
+                            account: 1,
+                            fractal_id: 1,
+                            old_root: Some(root(&first)),
+                            new_root: root(&second),
+                            old_leaf_count: Some(2),
+                            new_leaf_count: Some(3),
+                        },
+                    ]
+                );
+            });
+        }
+
+        #[test]
+        fn registration_for_minting() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_for_minting(1);
+
+                assert_eq!(
+                    data_capture_events().last(),
+                    Some(&crate::Event::RegisteredForMinting {
+                        account: 1,
+                        fractal_id: 1,
+                        period: 0,
+                    })
+                );
+            });
+        }
+
+        #[test]
+        fn minting_and_payout() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_for_minting(1);
+
+                run_to_next_minting();
+
+                let events = data_capture_events();
+                assert!(events.contains(&crate::Event::Minted {
+                    period: 0,
+                    total: max_reward_per_user(),
+                    per_user: max_reward_per_user(),
+                    number_of_accounts: 1,
+                    excess: FIRST_MINTING_TOTAL - max_reward_per_user(),
+                }));
+                assert_eq!(
+                    events.last(),
+                    Some(&crate::Event::PaidOut {
+                        account: 1,
+                        fractal_id: 1,
+                        period: 0,
+                        amount: max_reward_per_user(),
+                    })
+                );
+            });
+        }
+
+        #[test]
+        fn revocation() {
+            run_test(|| {
+                assert_ok!(FractalMinting::revoke_identity(Origin::root(), 1));
+
+                assert_eq!(
+                    data_capture_events(),
+                    vec![crate::Event::IdentityRevoked { fractal_id: 1 }]
+                );
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod identity_statuses {
+        use super::*;
+
+        #[test]
+        fn empty_without_identity() {
+            run_test(|| {
+                assert_eq!(FractalMinting::identity_statuses(&1), vec![]);
+            });
+        }
+
+        #[test]
+        fn reports_registered_identity_without_dataset() {
+            run_test(|| {
+                register_id_account(1, 1);
+
+                assert_eq!(
+                    FractalMinting::identity_statuses(&1),
+                    vec![IdentityStatus {
+                        fractal_id: 1,
+                        active: true,
+                        dataset_root: None,
+                        leaf_count: None,
+                        registered_for_minting: false,
+                    }]
+                );
+            });
+        }
+
+        #[test]
+        fn reports_dataset_and_registration() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_for_minting_dataset(1, &["a", "b", "c"]);
+
+                let tree = MerkleTree::<Blake2b>::from_iter(&["a", "b", "c"]).unwrap();
+                let statuses = FractalMinting::identity_statuses(&1);
+                assert_eq!(statuses.len(), 1);
+                assert_eq!(
+                    statuses[0].dataset_root,
+                    Some(sp_core::H512::from_slice(tree.hash()))
+                );
+                assert_eq!(statuses[0].leaf_count, Some(3));
+                assert!(statuses[0].registered_for_minting);
+
+                run_to_next_minting();
+
+                let statuses = FractalMinting::identity_statuses(&1);
+                assert!(!statuses[0].registered_for_minting);
+                assert_eq!(statuses[0].leaf_count, Some(3));
+            });
+        }
+
+        #[test]
+        fn registration_belongs_to_registering_account() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_id_account(1, 2);
+                register_for_minting(2);
+
+                assert!(!FractalMinting::identity_statuses(&1)[0].registered_for_minting);
+                assert!(FractalMinting::identity_statuses(&2)[0].registered_for_minting);
+            });
+        }
+
+        #[test]
+        fn reports_revoked_identity_as_inactive() {
+            run_test(|| {
+                register_id_account(1, 1);
+                assert_ok!(FractalMinting::revoke_identity(Origin::root(), 1));
+
+                assert!(!FractalMinting::identity_statuses(&1)[0].active);
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod signed_extension {
+        use super::*;
+        use frame_support::weights::DispatchInfo;
+        use sp_core::Pair;
+        use sp_runtime::{
+            traits::SignedExtension,
+            transaction_validity::{
+                InvalidTransaction, TransactionValidity, TransactionValidityError,
+            },
+            DispatchError,
+        };
+
+        fn validate(who: u64, call: crate::Call<Test>) -> TransactionValidity {
+            CheckDataCapture::<Test>::new().validate(
+                &who,
+                &mock::Call::FractalMinting(call),
+                &DispatchInfo::default(),
+                0,
+            )
+        }
+
+        fn registration(dataset: &[&str]) -> crate::Call<Test> {
+            crate::Call::register_for_minting(None, MerkleTree::from_iter(dataset).unwrap())
+        }
+
+        fn rejected_with(error: Error<Test>) -> TransactionValidity {
+            match DispatchError::from(error) {
+                DispatchError::Module { error, .. } => Err(TransactionValidityError::Invalid(
+                    InvalidTransaction::Custom(error),
+                )),
+                _ => unreachable!(),
+            }
+        }
+
+        #[test]
+        fn accepts_first_registration_in_period() {
+            run_test(|| {
+                register_id_account(1, 1);
+
+                let valid = validate(1, registration(&["a", "b"])).unwrap();
+                assert_eq!(valid.provides.len(), 1);
+            });
+        }
+
+        #[test]
+        fn rejects_second_registration_in_period() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_for_minting_dataset(1, &["a", "b"]);
+
+                assert_eq!(
+                    validate(1, registration(&["a", "b", "c"])),
+                    rejected_with(Error::<Test>::AlreadyRegisteredThisPeriod)
+                );
+
+                run_to_next_minting();
+                assert!(validate(1, registration(&["a", "b", "c"])).is_ok());
+            });
+        }
+
+        #[test]
+        fn rejects_non_extending_tree() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_for_minting_dataset(1, &["a", "b"]);
+                run_to_next_minting();
+
+                assert_eq!(
+                    validate(1, registration(&["a", "x", "c"])),
+                    rejected_with(Error::<Test>::ExtensionDoesNotExtendExistingDataset)
+                );
+            });
+        }
+
+        #[test]
+        fn rejects_registration_without_identity() {
+            run_test(|| {
+                assert_eq!(
+                    validate(1, registration(&["a", "b"])),
+                    rejected_with(Error::<Test>::NoIdentityRegistered)
+                );
+            });
+        }
+
+        #[test]
+        fn registrations_for_same_id_provide_same_tag() {
+            run_test(|| {
+                register_id_account(1, 1);
+                register_id_account(1, 2);
+
+                assert_eq!(
+                    validate(1, registration(&["a", "b"])).unwrap().provides,
+                    validate(2, registration(&["c", "d"])).unwrap().provides
+                );
+            });
+        }
+
+        #[test]
+        fn only_fractal_registers_identities() {
+            run_test(|| {
+                let call = || crate::Call::register_identity(1, 1);
+
+                assert!(validate(123, call()).is_ok());
+                assert_eq!(validate(1, call()), rejected_with(Error::<Test>::MustBeFractal));
+            });
+        }
+
+        #[test]
+        fn rejects_invalid_attestation() {
+            run_test(|| {
+                let attestation = IdentityAttestation {
+                    fractal_id: 1,
+                    account: 1,
+                    expires_at: 100,
+                };
+                let signature = fractal_key().sign(&attestation.signing_payload());
+                let call = |account| {
+                    crate::Call::register_identity_signed(
+                        IdentityAttestation {
+                            account,
+                            ..attestation.clone()
+                        },
+                        fractal_key().public(),
+                        signature.clone(),
+                    )
+                };
+
+                assert!(validate(1, call(1)).is_ok());
+                assert_eq!(
+                    validate(2, call(2)),
+                    rejected_with(Error::<Test>::InvalidAttestationSignature)
+                );
+            });
+        }
+
+        #[test]
+        fn signed_registrations_for_same_account_provide_same_tag() {
+            run_test(|| {
+                let call = |expires_at| {
+                    let attestation = IdentityAttestation {
+                        fractal_id: 1,
+                        account: 1,
+                        expires_at,
+                    };
+                    let signature = fractal_key().sign(&attestation.signing_payload());
+                    crate::Call::register_identity_signed(
+                        attestation,
+                        fractal_key().public(),
+                        signature,
+                    )
+                };
+
+                assert_eq!(
+                    validate(1, call(100)).unwrap().provides,
+                    validate(1, call(101)).unwrap().provides
+                );
+            });
+        }
+
+        #[test]
+        fn ignores_other_calls() {
+            run_test(|| {
+                assert!(validate(1, crate::Call::challenge_dataset(2, 2)).is_ok());
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod signed_registration {
+        use super::*;
+        use sp_core::{sr25519, Pair};
+
+        fn attestation(id: u64, account: u64) -> IdentityAttestation<u64, u64> {
+            IdentityAttestation {
+                fractal_id: id,
+                account,
+                expires_at: System::block_number() + 10,
+            }
+        }
+
+        fn signed(
+            attestation: &IdentityAttestation<u64, u64>,
+            key: &sr25519::Pair,
+        ) -> sr25519::Signature {
+            key.sign(&attestation.signing_payload())
+        }
+
+        #[test]
+        fn registers_identity_with_valid_attestation() {
+            run_test(|| {
+                let attestation = attestation(42, 1);
+                let signature = signed(&attestation, &fractal_key());
+
+                assert_ok!(FractalMinting::register_identity_signed(
+                    Origin::signed(1),
+                    attestation,
+                    fractal_key().public(),
+                    signature,
+                ));
+                register_for_minting(1);
+                run_to_next_minting();
+
+                assert_eq!(Balances::free_balance(&1), max_reward_per_user());
+            });
+        }
+
+        #[test]
+        fn rejects_attestation_for_different_account() {
+            run_test(|| {
+                let attestation = attestation(42, 1);
+                let signature = signed(&attestation, &fractal_key());
+
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(2),
+                        attestation,
+                        fractal_key().public(),
+                        signature,
+                    ),
+                    Error::<Test>::AttestationForDifferentAccount
+                );
+            });
+        }
+
+        #[test]
+        fn rejects_expired_attestation() {
+            run_test(|| {
+                let attestation = attestation(42, 1);
+                let signature = signed(&attestation, &fractal_key());
+
+                for _ in 0..11 {
+                    step_block();
+                }
+
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(1),
+                        attestation,
+                        fractal_key().public(),
+                        signature,
+                    ),
+                    Error::<Test>::AttestationExpired
+                );
+            });
+        }
+
+        #[test]
+        fn rejects_unknown_key() {
+            run_test(|| {
+                let other_key = sr25519::Pair::from_seed(&[1; 32]);
+                let attestation = attestation(42, 1);
+                let signature = signed(&attestation, &other_key);
+
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(1),
+                        attestation,
+                        other_key.public(),
+                        signature,
+                    ),
+                    Error::<Test>::UnknownFractalKey
+                );
+            });
+        }
+
+        #[test]
+        fn rejects_signature_from_other_key() {
+            run_test(|| {
+                let other_key = sr25519::Pair::from_seed(&[1; 32]);
+                let attestation = attestation(42, 1);
+                let signature = signed(&attestation, &other_key);
+
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(1),
+                        attestation,
+                        fractal_key().public(),
+                        signature,
+                    ),
+                    Error::<Test>::InvalidAttestationSignature
+                );
+            });
+        }
+
+        #[test]
+        fn rejects_tampered_attestation() {
+            run_test(|| {
+                let signature = signed(&attestation(42, 1), &fractal_key());
+
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(1),
+                        attestation(43, 1),
+                        fractal_key().public(),
+                        signature,
+                    ),
+                    Error::<Test>::InvalidAttestationSignature
+                );
+            });
+        }
+
+        #[test]
+        fn rejects_used_attestation() {
+            run_test(|| {
+                let attestation = attestation(42, 1);
+                let signature = signed(&attestation, &fractal_key());
+
+                assert_ok!(FractalMinting::register_identity_signed(
+                    Origin::signed(1),
+                    attestation.clone(),
+                    fractal_key().public(),
+                    signature.clone(),
+                ));
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(1),
+                        attestation,
+                        fractal_key().public(),
+                        signature,
+                    ),
+                    Error::<Test>::AttestationAlreadyUsed
+                );
+            });
+        }
+
+        #[test]
+        fn rejects_already_registered_account() {
+            run_test(|| {
+                let first = attestation(42, 1);
+                let signature = signed(&first, &fractal_key());
+                assert_ok!(FractalMinting::register_identity_signed(
+                    Origin::signed(1),
+                    first,
+                    fractal_key().public(),
+                    signature,
+                ));
+
+                step_block();
+                let second = attestation(42, 1);
+                let signature = signed(&second, &fractal_key());
+
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(1),
+                        second,
+                        fractal_key().public(),
+                        signature,
+                    ),
+                    Error::<Test>::AlreadyRegisteredToFractalId
+                );
+            });
+        }
+
+        #[test]
+        fn attestation_cannot_be_replayed_after_migration() {
+            run_test(|| {
+                let attestation = attestation(42, 1);
+                let signature = signed(&attestation, &fractal_key());
+                assert_ok!(FractalMinting::register_identity_signed(
+                    Origin::signed(1),
+                    attestation.clone(),
+                    fractal_key().public(),
+                    signature.clone(),
+                ));
+                register_for_minting(1);
+
+                register_id_account(42, 2);
+                assert_ok!(FractalMinting::migrate_identity(Origin::signed(2), 42, 1));
+
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(1),
+                        attestation,
+                        fractal_key().public(),
+                        signature,
+                    ),
+                    Error::<Test>::AttestationAlreadyUsed
+                );
+                assert_noop!(
+                    FractalMinting::migrate_identity(Origin::signed(1), 42, 2),
+                    Error::<Test>::FractalIdNotRegisteredToAccount
+                );
+            });
+        }
+
+        #[test]
+        fn migrated_account_cannot_register_again() {
+            run_test(|| {
+                register_id_account(42, 1);
+                register_id_account(42, 2);
+                assert_ok!(FractalMinting::migrate_identity(Origin::signed(2), 42, 1));
+
+                let attestation = attestation(42, 1);
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(1),
+                        attestation.clone(),
+                        fractal_key().public(),
+                        signed(&attestation, &fractal_key()),
+                    ),
+                    Error::<Test>::AccountMigratedAway
+                );
+                assert_noop!(
+                    FractalMinting::register_identity(Origin::signed(123), 42, 1),
+                    Error::<Test>::AccountMigratedAway
+                );
+            });
+        }
+
+        #[test]
+        fn root_can_add_fractal_keys() {
+            run_test(|| {
+                let new_key = sr25519::Pair::from_seed(&[1; 32]);
+                assert_ok!(FractalMinting::set_fractal_public_key(
+                    Origin::root(),
+                    new_key.public(),
+                    true
+                ));
+
+                let attestation = attestation(42, 1);
+                let signature = signed(&attestation, &new_key);
+
+                assert_ok!(FractalMinting::register_identity_signed(
+                    Origin::signed(1),
+                    attestation,
+                    new_key.public(),
+                    signature,
+                ));
+            });
+        }
+
+        #[test]
+        fn root_can_remove_fractal_keys() {
+            run_test(|| {
+                assert_ok!(FractalMinting::set_fractal_public_key(
+                    Origin::root(),
+                    fractal_key().public(),
+                    false
+                ));
+
+                let attestation = attestation(42, 1);
+                let signature = signed(&attestation, &fractal_key());
+
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(1),
+                        attestation,
+                        fractal_key().public(),
+                        signature,
+                    ),
+                    Error::<Test>::UnknownFractalKey
+                );
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod identity_lifecycle {
+        use super::*;
+        use sp_core::Pair;
+
+        #[test]
+        fn revoked_identity_cannot_register_for_minting() {
+            run_test(|| {
+                register_id_account(42, 1);
+                assert_ok!(FractalMinting::revoke_identity(Origin::signed(123), 42));
+
+                assert_noop!(
+                    FractalMinting::register_for_minting(
+                        Origin::signed(1),
+                        None,
+                        simple_tree().prune_balanced()
+                    ),
+                    Error::<Test>::IdentityRevoked
+                );
+            });
+        }
+
+        #[test]
+        fn revocation_removes_pending_minting() {
+            run_test(|| {
+                register_id_account(42, 1);
+                register_for_minting(1);
+
+                assert_ok!(FractalMinting::revoke_identity(Origin::root(), 42));
+                run_to_next_minting();
+
+                assert_eq!(Balances::free_balance(&1), 0);
+            });
+        }
+
+        #[test]
+        fn revocation_requires_fractal() {
+            run_test(|| {
+                register_id_account(42, 1);
+
+                assert_noop!(
+                    FractalMinting::revoke_identity(Origin::signed(1), 42),
+                    Error::<Test>::MustBeFractal
+                );
+            });
+        }
+
+        #[test]
+        fn registering_again_reinstates_identity() {
+            run_test(|| {
+                register_id_account(42, 1);
+                assert_ok!(FractalMinting::revoke_identity(Origin::signed(123), 42));
+
+                register_id_account(42, 1);
+                register_for_minting(1);
+                run_to_next_minting();
+
+                assert_eq!(Balances::free_balance(&1), max_reward_per_user());
+            });
+        }
+
+        #[test]
+        fn revoked_identity_cannot_self_register() {
+            run_test(|| {
+                assert_ok!(FractalMinting::revoke_identity(Origin::signed(123), 42));
+
+                let attestation = IdentityAttestation {
+                    fractal_id: 42,
+                    account: 1,
+                    expires_at: 100,
+                };
+                let signature = fractal_key().sign(&attestation.signing_payload());
+
+                assert_noop!(
+                    FractalMinting::register_identity_signed(
+                        Origin::signed(1),
+                        attestation,
+                        fractal_key().public(),
+                        signature,
+                    ),
+                    Error::<Test>::IdentityRevoked
+                );
+            });
+        }
+
+        #[test]
+        fn expired_identity_cannot_register_for_minting() {
+            run_test(|| {
+                register_id_account(42, 1);
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::signed(123),
+                    42,
+                    Some(System::block_number() + 5)
+                ));
+
+                for _ in 0..5 {
+                    step_block();
+                }
+
+                assert_noop!(
+                    FractalMinting::register_for_minting(
+                        Origin::signed(1),
+                        None,
+                        simple_tree().prune_balanced()
+                    ),
+                    Error::<Test>::IdentityExpired
+                );
+            });
+        }
+
+        #[test]
+        fn identity_can_register_before_expiry() {
+            run_test(|| {
+                register_id_account(42, 1);
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::signed(123),
+                    42,
+                    Some(System::block_number() + 5)
+                ));
+
+                register_for_minting(1);
+            });
+        }
+
+        #[test]
+        fn expiry_can_be_cleared() {
+            run_test(|| {
+                register_id_account(42, 1);
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::signed(123),
+                    42,
+                    Some(System::block_number())
+                ));
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::signed(123),
+                    42,
+                    None
+                ));
+
+                register_for_minting(1);
+            });
+        }
+
+        #[test]
+        fn migration_moves_dataset_to_new_account() {
+            run_test(|| {
+                register_id_account(42, 1);
+                register_for_minting_dataset(1, &["a", "b"]);
+
+                register_id_account(42, 2);
+                assert_ok!(FractalMinting::migrate_identity(Origin::signed(2), 42, 1));
+
+                assert_noop!(
+                    FractalMinting::register_for_minting(
+                        Origin::signed(2),
+                        None,
+                        MerkleTree::from_iter(&["a", "b"]).unwrap(),
+                    ),
+                    Error::<Test>::ExtensionDoesNotExtendExistingDataset
+                );
+                let post = register_for_minting_dataset(2, &["a", "b", "c"]);
+                assert_eq!(post.pays_fee, Pays::Yes);
+            });
+        }
+
+        #[test]
+        fn migration_removes_identity_from_old_account() {
+            run_test(|| {
+                register_id_account(42, 1);
+                register_id_account(42, 2);
+                assert_ok!(FractalMinting::migrate_identity(Origin::signed(2), 42, 1));
+
+                assert_noop!(
+                    FractalMinting::register_for_minting(
+                        Origin::signed(1),
+                        None,
+                        simple_tree().prune_balanced()
+                    ),
+                    Error::<Test>::NoIdentityRegistered
+                );
+            });
+        }
+
+        #[test]
+        fn migration_moves_pending_minting() {
+            run_test(|| {
+                register_id_account(42, 1);
+                register_for_minting(1);
+
+                register_id_account(42, 2);
+                assert_ok!(FractalMinting::migrate_identity(Origin::signed(2), 42, 1));
+                run_to_next_minting();
+
+                assert_eq!(Balances::free_balance(&1), 0);
+                assert_eq!(Balances::free_balance(&2), max_reward_per_user());
+            });
+        }
+
+        #[test]
+        fn migration_requires_new_account_registered() {
+            run_test(|| {
+                register_id_account(42, 1);
+                register_for_minting(1);
+
+                assert_noop!(
+                    FractalMinting::migrate_identity(Origin::signed(2), 42, 1),
+                    Error::<Test>::FractalIdNotRegisteredToAccount
+                );
+            });
+        }
+
+        #[test]
+        fn migration_does_not_overwrite_existing_dataset() {
+            run_test(|| {
+                register_id_account(42, 1);
+                register_id_account(42, 2);
+                register_for_minting(1);
+                register_for_minting(2);
+
+                assert_noop!(
+                    FractalMinting::migrate_identity(Origin::signed(2), 42, 1),
+                    Error::<Test>::DatasetAlreadyExists
+                );
+            });
+        }
+
+        #[test]
+        fn revoked_identity_cannot_migrate() {
+            run_test(|| {
+                register_id_account(42, 1);
+                register_id_account(42, 2);
+                assert_ok!(FractalMinting::revoke_identity(Origin::signed(123), 42));
+
+                assert_noop!(
+                    FractalMinting::migrate_identity(Origin::signed(2), 42, 1),
+                    Error::<Test>::IdentityRevoked
+                );
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod extension_proofs {
+        use super::*;
+
+        #[test]
+        fn second_proof_does_not_extend_initial_proof() {
+            run_test(|| {
+                register_id_account(1, 1);
+                assert_ok!(FractalMinting::register_for_minting(
+                    Origin::signed(1),
+                    None,
+                    simple_tree().prune_balanced()
+                ));
+                assert_noop!(
+                    FractalMinting::register_for_minting(
+                        Origin::signed(1),
+                        None,
+                        simple_tree().prune_balanced()
+                    ),
+                    Error::<Test>::ExtensionDoesNotExtendExistingDataset
+                );
+            });
+        }
+
+        #[test]
+        fn multiple_identities_requires_specifying_identity() {
+            run_test(|| {
+                register_id_account(42, 1);
+                register_id_account(43, 1);
+
+                assert_noop!(
+                    FractalMinting::register_for_minting(
+                        Origin::signed(1),
+                        None,
+                        simple_tree().prune_balanced()
+                    ),
+                    Error::<Test>::MustSpecifyFractalIdWithMultipleIds
+                );
+            });
+        }
+
+        #[test]
+        fn provided_identity_not_registered() {
+            run_test(|| {
+                register_id_account(42, 1);
+
+                assert_noop!(
+                    FractalMinting::register_for_minting(
+                        Origin::signed(1),
+                        Some(43),
+                        simple_tree().prune_balanced()
+                    ),
+                    Error::<Test>::FractalIdNotRegisteredToAccount
+                );
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod verified_bonus {
+        use super::*;
+        use frame_support::traits::Currency;
+        use sp_runtime::Perbill;
+
+        const LOCK: u64 = 100;
+
+        fn run_bonus_test(f: impl FnOnce()) {
+            run_test(|| {
+                assert_ok!(FractalStaking::set_lock_period_shares(
+                    Origin::root(),
+                    LOCK,
+                    1
+                ));
+                assert_ok!(FractalStaking::set_verified_bonus(
+                    Origin::root(),
+                    Perbill::from_percent(50)
+                ));
+
+                let _ = Balances::deposit_creating(&1, 1_000);
+                assert_ok!(FractalStaking::stake(Origin::signed(1), LOCK, 1_000));
+
+                f();
+            });
+        }
+
+        fn has_bonus(account: u64) -> bool {
+            fractal_staking::VerifiedStakers::<Test>::contains_key(account)
+        }
+
+        #[test]
+        fn registering_identity_applies_bonus() {
+            run_bonus_test(|| {
+                register_id_account(1, 1);
+
+                assert!(has_bonus(1));
+                assert_eq!(FractalStaking::effective_total_coin_shares(), 1_500);
+            });
+        }
+
+        #[test]
+        fn migrating_identity_removes_bonus() {
+            run_bonus_test(|| {
+                register_id_account(1, 1);
+                register_id_account(1, 2);
+
+                assert_ok!(FractalMinting::migrate_identity(Origin::signed(2), 1, 1));
+
+                assert!(!has_bonus(1));
+                assert_eq!(FractalStaking::effective_total_coin_shares(), 1_000);
+                assert_eq!(crate::IdentityAccounts::<Test>::get(1), vec![2]);
+            });
+        }
+
+        #[test]
+        fn revoking_identity_removes_bonus() {
+            run_bonus_test(|| {
+                register_id_account(1, 1);
+
+                assert_ok!(FractalMinting::revoke_identity(Origin::root(), 1));
+
+                assert!(!has_bonus(1));
+                assert_eq!(FractalStaking::effective_total_coin_shares(), 1_000);
+            });
+        }
+
+        #[test]
+        fn reinstating_identity_restores_bonus_of_every_account() {
+            run_bonus_test(|| {
+                let _ = Balances::deposit_creating(&2, 1_000);
+                assert_ok!(FractalStaking::stake(Origin::signed(2), LOCK, 1_000));
+                register_id_account(1, 1);
+                register_id_account(1, 2);
+                assert_ok!(FractalMinting::revoke_identity(Origin::root(), 1));
+
+                register_id_account(1, 1);
+
+                assert!(has_bonus(1));
+                assert!(has_bonus(2));
+            });
+        }
+
+        #[test]
+        fn expiring_identity_removes_bonus() {
+            run_bonus_test(|| {
+                register_id_account(1, 1);
+                let expires_at = System::block_number() + 2;
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::root(),
+                    1,
+                    Some(expires_at)
+                ));
+
+                step_block();
+                assert!(has_bonus(1));
+
+                step_block();
+                assert_eq!(System::block_number(), expires_at);
+                assert!(!has_bonus(1));
+            });
+        }
+
+        #[test]
+        fn past_expiry_removes_bonus_at_once() {
+            run_bonus_test(|| {
+                register_id_account(1, 1);
+
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::root(),
+                    1,
+                    Some(System::block_number())
+                ));
+
+                assert!(!has_bonus(1));
+            });
+        }
+
+        #[test]
+        fn postponed_expiry_keeps_bonus() {
+            run_bonus_test(|| {
+                register_id_account(1, 1);
+                let expires_at = System::block_number() + 2;
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::root(),
+                    1,
+                    Some(expires_at)
+                ));
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::root(),
+                    1,
+                    Some(expires_at + 10)
+                ));
+
+                step_block();
+                step_block();
+
+                assert!(has_bonus(1));
+                assert_eq!(crate::ExpiringIdentities::<Test>::get(expires_at), vec![]);
+                assert_eq!(
+                    crate::ExpiringIdentities::<Test>::get(expires_at + 10),
+                    vec![1]
+                );
+            });
+        }
+
+        #[test]
+        fn removing_expiry_restores_bonus() {
+            run_bonus_test(|| {
+                register_id_account(1, 1);
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::root(),
+                    1,
+                    Some(System::block_number())
+                ));
+
+                assert_ok!(FractalMinting::set_identity_expiry(Origin::root(), 1, None));
+
+                assert!(has_bonus(1));
+            });
+        }
+
+        #[test]
+        fn new_bonus_applies_to_verified_stakes() {
+            run_bonus_test(|| {
+                register_id_account(1, 1);
+
+                assert_ok!(FractalStaking::set_verified_bonus(
+                    Origin::root(),
+                    Perbill::from_percent(100)
+                ));
+
+                assert_eq!(FractalStaking::effective_total_coin_shares(), 2_000);
+            });
+        }
+
+        #[test]
+        fn limits_accounts_per_identity() {
+            run_test(|| {
+                let max = <Test as crate::Config>::MaxAccountsPerIdentity::get();
+                for account in 0..max as u64 {
+                    register_id_account(1, account);
+                }
+                // Registering an account again doesn't add it twice.
+                register_id_account(1, 0);
+
+                assert_noop!(
+                    FractalMinting::register_identity(Origin::signed(123), 1, max as u64),
+                    Error::<Test>::TooManyAccounts
+                );
+            });
+        }
+
+        #[test]
+        fn limits_expiries_per_block() {
+            run_test(|| {
+                let expires_at = System::block_number() + 2;
+                let max = <Test as crate::Config>::MaxExpiriesPerBlock::get();
+                for id in 0..max as u64 {
+                    assert_ok!(FractalMinting::set_identity_expiry(
+                        Origin::root(),
+                        id,
+                        Some(expires_at)
+                    ));
+                }
+                // Setting an expiry again doesn't take another place.
+                assert_ok!(FractalMinting::set_identity_expiry(
+                    Origin::root(),
+                    0,
+                    Some(expires_at)
+                ));
+
+                assert_noop!(
+                    FractalMinting::set_identity_expiry(
+                        Origin::root(),
+                        max as u64,
+                        Some(expires_at)
+                    ),
+                    Error::<Test>::TooManyExpiries
+                );
+            });
+        }
+
+        #[test]
+        fn migration_indexes_accounts_and_expiries() {
+            run_bonus_test(|| {
+                crate::AccountIds::<Test>::insert(1, 1, ());
+                crate::AccountIds::<Test>::insert(2, 1, ());
+                crate::RevokedIdentities::<Test>::insert(1, ());
+                let expires_at = System::block_number() + 5;
+                crate::IdentityExpiries::<Test>::insert(2, expires_at);
+                // Verified before the revocation, which staking wasn't told about.
+                fractal_staking::VerifiedStakers::<Test>::insert(1, ());
+                fractal_staking::VerifiedCoinShares::<Test>::put(1_000);
+                crate::StorageVersion::<Test>::put(crate::Releases::V3);
+
+                crate::migrations::migrate::<Test>();
+
+                let mut accounts = crate::IdentityAccounts::<Test>::get(1);
+                accounts.sort();
+                assert_eq!(accounts, vec![1, 2]);
+                assert_eq!(crate::ExpiringIdentities::<Test>::get(expires_at), vec![2]);
+                assert!(!has_bonus(1));
+                assert_eq!(FractalStaking::effective_total_coin_shares(), 1_000);
+                assert_eq!(crate::StorageVersion::<Test>::get(), crate::Releases::V4);
+            });
+        }
+    }
 }
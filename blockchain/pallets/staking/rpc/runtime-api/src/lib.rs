@@ -0,0 +1,25 @@
+//! Runtime API for querying an account's stakes and rewards.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+pub use fractal_staking::{RollOver, StakePosition, StakingSummary};
+
+sp_api::decl_runtime_apis! {
+    #[api_version(2)]
+    pub trait StakingApi<AccountId, BlockNumber, Balance>
+    where
+        AccountId: Codec,
+        BlockNumber: Codec,
+        Balance: Codec,
+    {
+        /// Stakes held by `account`, its share of staking rewards and an estimate of its reward
+        /// at the next distribution.
+        fn staking_summary(account: AccountId) -> StakingSummary<BlockNumber, Balance>;
+
+        /// Lock periods that can currently be staked for, with their shares per coin.
+        fn lock_period_tiers() -> Vec<(BlockNumber, u32)>;
+    }
+}
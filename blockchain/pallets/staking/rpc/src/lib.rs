@@ -0,0 +1,102 @@
+//! RPC interface for the staking pallet.
+
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use fractal_staking_rpc_runtime_api::{
+    RollOver, StakePosition, StakingApi as StakingRuntimeApi, StakingSummary,
+};
+
+pub use self::gen_client::Client as StakingClient;
+
+#[rpc]
+pub trait StakingApi<BlockHash, AccountId, BlockNumber, ResponseType> {
+    /// Stakes held by `account`, its share of staking rewards and an estimate of its reward at
+    /// the next distribution.
+    #[rpc(name = "fractal_stakingSummary")]
+    fn staking_summary(&self, account: AccountId, at: Option<BlockHash>) -> Result<ResponseType>;
+
+    /// Lock periods that can currently be staked for, with their shares per coin.
+    #[rpc(name = "fractal_lockPeriodTiers")]
+    fn lock_period_tiers(&self, at: Option<BlockHash>) -> Result<Vec<(BlockNumber, u32)>>;
+}
+
+/// Implements `StakingApi` by calling into the runtime.
+pub struct Staking<C, B> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Staking<C, B> {
+    pub fn new(client: Arc<C>) -> Self {
+        Staking {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+/// Error codes returned by the staking RPC.
+pub enum Error {
+    RuntimeError,
+}
+
+impl From<Error> for i64 {
+    fn from(e: Error) -> i64 {
+        match e {
+            Error::RuntimeError => 1,
+        }
+    }
+}
+
+impl<C, Block, AccountId, BlockNumber, Balance>
+    StakingApi<
+        <Block as BlockT>::Hash,
+        AccountId,
+        BlockNumber,
+        StakingSummary<BlockNumber, Balance>,
+    > for Staking<C, Block>
+where
+    Block: BlockT,
+    C: Send + Sync + 'static,
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+    C::Api: StakingRuntimeApi<Block, AccountId, BlockNumber, Balance>,
+    AccountId: Codec,
+    BlockNumber: Codec,
+    Balance: Codec,
+{
+    fn staking_summary(
+        &self,
+        account: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<StakingSummary<BlockNumber, Balance>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.staking_summary(&at, account).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError.into()),
+            message: "Unable to query staking summary.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn lock_period_tiers(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<(BlockNumber, u32)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.lock_period_tiers(&at).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError.into()),
+            message: "Unable to query lock period tiers.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+}
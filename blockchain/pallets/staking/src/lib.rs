@@ -2,27 +2,94 @@
 
 pub use pallet::*;
 
+pub mod migrations;
+
 #[cfg(test)]
 mod mock;
 #[cfg(test)]
 mod tests;
 
+/// Lets other pallets require and slash stakes.
+pub trait StakeInspector<AccountId, BlockNumber, Balance> {
+    /// Amount `who` has staked that stays locked until at least `until`.
+    fn staked_until(who: &AccountId, until: BlockNumber) -> Balance;
+
+    /// Moves up to `amount` of `who`'s stake to `beneficiary`, returning the amount moved.
+    fn slash(who: &AccountId, amount: Balance, beneficiary: &AccountId) -> Balance;
+}
+
+/// Decides which accounts belong to verified people, whose stakes get `VerifiedBonus`.
+pub trait IsVerified<AccountId> {
+    fn is_verified(who: &AccountId) -> bool;
+}
+
+impl<AccountId> IsVerified<AccountId> for () {
+    fn is_verified(_: &AccountId) -> bool {
+        false
+    }
+}
+
+/// Lets the `IsVerified` provider tell staking when an account may have been verified or
+/// unverified.
+pub trait OnVerificationChanged<AccountId> {
+    /// Returns the weight used, which grows with the stakes of `who`.
+    fn on_verification_changed(who: &AccountId) -> frame_support::weights::Weight;
+
+    /// Upper bound on the weight `on_verification_changed` uses, for declaring call weights.
+    fn on_verification_changed_weight() -> frame_support::weights::Weight;
+}
+
+impl<AccountId> OnVerificationChanged<AccountId> for () {
+    fn on_verification_changed(_: &AccountId) -> frame_support::weights::Weight {
+        0
+    }
+
+    fn on_verification_changed_weight() -> frame_support::weights::Weight {
+        0
+    }
+}
+
+/// Lets other pallets keep stakes they rely on from being unstaked early.
+pub trait StakeLocks<AccountId, BlockNumber, Balance> {
+    /// Amount `who` must keep staked until at least the returned block, if any.
+    fn stake_lock(who: &AccountId) -> Option<(BlockNumber, Balance)>;
+}
+
+impl<AccountId, BlockNumber, Balance> StakeLocks<AccountId, BlockNumber, Balance> for () {
+    fn stake_lock(_: &AccountId) -> Option<(BlockNumber, Balance)> {
+        None
+    }
+}
+
 #[frame_support::pallet]
 pub mod pallet {
     use frame_support::pallet_prelude::*;
     use frame_system::pallet_prelude::*;
 
-    use codec::alloc::collections::BTreeMap;
-    use core::{convert::TryFrom, ops::Add};
+    use core::convert::TryFrom;
     use frame_support::{
         inherent::Vec,
-        traits::{Currency, ExistenceRequirement, Get, ReservableCurrency},
+        storage::bounded_vec::BoundedVec,
+        traits::{BalanceStatus, Currency, Get, OnUnbalanced, ReservableCurrency},
+        weights::Weight,
     };
     use frame_system::ensure_signed;
-    use num_bigint::BigUint;
+    #[cfg(feature = "std")]
+    use serde::{Deserialize, Serialize};
+    use sp_arithmetic::{
+        helpers_128bit::multiply_by_rational,
+        traits::{
+            AtLeast32BitUnsigned, Bounded, Saturating, UniqueSaturatedFrom, UniqueSaturatedInto,
+            Zero,
+        },
+        FixedPointNumber, FixedU128, PerThing, Perbill,
+    };
 
-    type BalanceOf<T> =
+    pub type BalanceOf<T> =
         <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+    pub type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+        <T as frame_system::Config>::AccountId,
+    >>::NegativeImbalance;
 
     #[pallet::config]
     pub trait Config: frame_system::Config + pallet_balances::Config {
@@ -32,13 +99,53 @@ pub mod pallet {
         type DistributeEveryNBlocks: Get<Self::BlockNumber>;
 
         type DistributionSource: Get<Self::AccountId>;
+
+        /// Receives the penalty for unstaking early. `()` burns it.
+        type EarlyUnstakePenaltyHandler: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+        /// Accounts whose stakes get `VerifiedBonus`. `()` verifies no one.
+        type Verification: crate::IsVerified<Self::AccountId>;
+
+        /// Upper bound on the number of stakes released in a single distribution block. Stakes past
+        /// it are released at the next distribution with room for them.
+        type MaxMaturingPerBlock: Get<u32>;
+
+        /// Stakes that can't be unstaked early.
+        type StakeLocks: crate::StakeLocks<Self::AccountId, Self::BlockNumber, BalanceOf<Self>>;
+
+        /// Upper bound on the number of stakes an account holds, since applying `VerifiedBonus`
+        /// updates each of them.
+        type MaxStakesPerAccount: Get<u32>;
+    }
+
+    /// Sends early unstaking penalties to `DistributionSource`, to be shared among the remaining
+    /// stakers at the next distribution.
+    pub struct Redistribute<T>(PhantomData<T>);
+
+    impl<T: Config> OnUnbalanced<NegativeImbalanceOf<T>> for Redistribute<T> {
+        fn on_nonzero_unbalanced(amount: NegativeImbalanceOf<T>) {
+            T::Currency::resolve_creating(&T::DistributionSource::get(), amount);
+        }
     }
 
     #[pallet::storage]
     pub type MinimumStake<T: Config> = StorageValue<_, BalanceOf<T>, OptionQuery>;
 
+    /// Portion of a stake given up to unstake it before it matures. Early unstaking is not allowed
+    /// if unset.
     #[pallet::storage]
-    pub type TotalCoinShares<T: Config> = StorageValue<_, EncodableBigUint, ValueQuery>;
+    pub type EarlyUnstakePenalty<T: Config> = StorageValue<_, Perbill, OptionQuery>;
+
+    /// Sum of the coin shares of every stake, not counting `VerifiedBonus`. `stake` rejects stakes
+    /// that would overflow it, so no stake's coin shares can overflow either. Roll-overs and
+    /// bonuses saturate, which needs the total issuance times twice the largest `LockPeriodShares`
+    /// to exceed `u128::MAX`.
+    #[pallet::storage]
+    pub type TotalCoinShares<T: Config> = StorageValue<_, u128, ValueQuery>;
+
+    /// Sum of the coin shares of the stakes of `VerifiedStakers`, which `VerifiedBonus` adds to.
+    #[pallet::storage]
+    pub type VerifiedCoinShares<T: Config> = StorageValue<_, u128, ValueQuery>;
 
     #[pallet::storage]
     pub type StakedAmounts<T: Config> = StorageDoubleMap<
@@ -51,25 +158,262 @@ pub mod pallet {
         ValueQuery,
     >;
 
+    /// Shares per coin given to new stakes locked for each number of blocks. Stakes keep the
+    /// shares they were made with if their tier is changed or retired.
     #[pallet::storage]
     pub type LockPeriodShares<T: Config> =
         StorageMap<_, Blake2_128Concat, BlockNumberFor<T>, u32, OptionQuery>;
 
+    /// Extra coin shares given to the stakes of verified accounts, as a portion of their coin
+    /// shares.
+    #[pallet::storage]
+    pub type VerifiedBonus<T: Config> = StorageValue<_, Perbill, ValueQuery>;
+
+    /// Accounts whose stakes get `VerifiedBonus`, as of the last time staking checked their
+    /// verification.
+    #[pallet::storage]
+    pub type VerifiedStakers<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, (), OptionQuery>;
+
+    /// Rewards distributed per coin share since staking began. Distributed rewards stay reserved
+    /// in `DistributionSource` until they're paid.
+    ///
+    /// Each distribution rounds its increment up by `10^-18`, so a stake's rewards from it are off
+    /// by at most one unit plus its coin shares times `10^-18`. This saturates once more than
+    /// `u128::MAX / 10^18` units have been distributed per coin share.
+    #[pallet::storage]
+    pub type RewardPerCoinShare<T: Config> = StorageValue<_, FixedU128, ValueQuery>;
+
+    /// Rewards distributed per coin share of `VerifiedStakers`, which is `RewardPerCoinShare`
+    /// increased by `VerifiedBonus` as it was at each distribution. Changing the bonus applies to
+    /// every verified stake from the next distribution without revisiting them.
+    #[pallet::storage]
+    pub type RewardPerVerifiedCoinShare<T: Config> = StorageValue<_, FixedU128, ValueQuery>;
+
+    /// `RewardPerCoinShare`, or `RewardPerVerifiedCoinShare` for `VerifiedStakers`, as of the last
+    /// time each stake's rewards were paid.
+    #[pallet::storage]
+    pub type RewardCheckpoints<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        BlockNumberFor<T>,
+        FixedU128,
+        ValueQuery,
+    >;
+
+    /// Stakes to release at each distribution block, as keys of `StakedAmounts`.
+    #[pallet::storage]
+    pub type Maturing<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        BlockNumberFor<T>,
+        BoundedVec<(T::AccountId, BlockNumberFor<T>), T::MaxMaturingPerBlock>,
+        ValueQuery,
+    >;
+
+    /// Who paid for and who manages stakes made with `stake_for`. Other stakes are paid for and
+    /// managed by the account they're staked for.
+    #[pallet::storage]
+    pub type DelegatedStakes<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        BlockNumberFor<T>,
+        StakeRoles<T::AccountId>,
+        OptionQuery,
+    >;
+
+    /// What to do with stakes when they mature, if not returning them.
+    #[pallet::storage]
+    pub type RollOvers<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        BlockNumberFor<T>,
+        RollOver<BlockNumberFor<T>>,
+        OptionQuery,
+    >;
+
+    #[pallet::storage]
+    pub(super) type StorageVersion<T: Config> = StorageValue<_, Releases, ValueQuery>;
+
+    /// Accounts that pay for and manage a stake on behalf of the one it's staked for.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    pub struct StakeRoles<AccountId> {
+        /// Has the stake reserved, and gets it back when it's released.
+        pub payer: AccountId,
+        /// Can unstake early and set roll-overs.
+        pub controller: AccountId,
+    }
+
+    /// Restakes a matured stake for another lock period rather than returning it.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+    pub struct RollOver<BlockNumber> {
+        /// Tier of `LockPeriodShares` to stake into.
+        pub lock_period: BlockNumber,
+        /// Whether the stake's rewards are added to the new stake instead of being paid out.
+        pub compound: bool,
+    }
+
+    /// Amount staked in one tier of a stake, as returned by `StakingApi`.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+    pub struct StakePosition<BlockNumber, Balance> {
+        pub unstake_at: BlockNumber,
+        /// Shares per coin of the `LockPeriodShares` tier staked into.
+        pub shares: u32,
+        pub balance: Balance,
+        pub roll_over: Option<RollOver<BlockNumber>>,
+    }
+
+    /// An account's stakes and rewards, as returned by `StakingApi`.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+    pub struct StakingSummary<BlockNumber, Balance> {
+        pub positions: Vec<StakePosition<BlockNumber, Balance>>,
+        /// Rewards already distributed to the account's stakes but not yet paid.
+        pub pending_rewards: Balance,
+        /// The account's coin shares as a fraction of every stake's, including bonuses.
+        pub coin_shares_fraction: Perbill,
+        /// Reward at the next distribution if `DistributionSource`'s free balance and every stake
+        /// stay as they are.
+        pub estimated_next_reward: Balance,
+    }
+
+    #[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Releases {
+        V1,
+        /// Rewards accrue through `RewardPerCoinShare`, and stakes are released from `Maturing`.
+        V2,
+        /// Coin shares and rewards per coin share are fixed width.
+        V3,
+    }
+
+    impl Default for Releases {
+        fn default() -> Self {
+            Releases::V1
+        }
+    }
+
+    #[pallet::genesis_config]
+    pub struct GenesisConfig<T: Config> {
+        /// Lock periods in blocks, with the shares per coin of their `LockPeriodShares` tier.
+        pub lock_period_shares: Vec<(T::BlockNumber, u32)>,
+        pub minimum_stake: Option<BalanceOf<T>>,
+    }
+
+    #[cfg(feature = "std")]
+    impl<T: Config> Default for GenesisConfig<T> {
+        fn default() -> Self {
+            GenesisConfig {
+                lock_period_shares: Vec::new(),
+                minimum_stake: None,
+            }
+        }
+    }
+
+    #[pallet::genesis_build]
+    impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+        fn build(&self) {
+            StorageVersion::<T>::put(Releases::V3);
+            MinimumStake::<T>::set(self.minimum_stake);
+
+            for (lock_period, shares) in &self.lock_period_shares {
+                LockPeriodShares::<T>::insert(lock_period, shares);
+            }
+        }
+    }
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
     pub struct Pallet<T>(_);
 
     #[pallet::event]
-    #[pallet::metadata(BalanceOf<T> = "Balance")]
+    #[pallet::metadata(BalanceOf<T> = "Balance", T::BlockNumber = "BlockNumber")]
     #[pallet::generate_deposit(pub fn deposit_event)]
     pub enum Event<T: Config> {
+        /// `amount` was shared among stakes, bringing `RewardPerCoinShare` to
+        /// `reward_per_coin_share`. Each stake's share is its coin shares times the increase.
         Distribution {
             amount: BalanceOf<T>,
+            reward_per_coin_share: FixedU128,
+        },
+        Staked {
+            amount: BalanceOf<T>,
+            who: T::AccountId,
+            unstake_at: T::BlockNumber,
+            payer: T::AccountId,
+            controller: T::AccountId,
+            /// Coin shares added to the stake, including any bonus.
+            coin_shares: u128,
         },
+        RewardsPaid {
+            amount: BalanceOf<T>,
+            who: T::AccountId,
+            unstake_at: T::BlockNumber,
+        },
+        /// A stake was released, returning its balance. `amount` includes the rewards paid with
+        /// it.
         Unstaked {
             amount: BalanceOf<T>,
             who: T::AccountId,
         },
+        Slashed {
+            amount: BalanceOf<T>,
+            who: T::AccountId,
+            beneficiary: T::AccountId,
+        },
+        UnstakedEarly {
+            amount: BalanceOf<T>,
+            penalty: BalanceOf<T>,
+            who: T::AccountId,
+        },
+        RolledOver {
+            amount: BalanceOf<T>,
+            who: T::AccountId,
+            unstake_at: T::BlockNumber,
+        },
+        LockPeriodSharesSet {
+            lock_period: T::BlockNumber,
+            shares: u32,
+            previous: Option<u32>,
+        },
+        LockPeriodRetired {
+            lock_period: T::BlockNumber,
+            shares: u32,
+        },
+        RollOverSet {
+            who: T::AccountId,
+            unstake_at: T::BlockNumber,
+            roll_over: Option<RollOver<T::BlockNumber>>,
+        },
+        MinimumStakeSet {
+            min: Option<BalanceOf<T>>,
+        },
+        EarlyUnstakePenaltySet {
+            penalty: Option<Perbill>,
+        },
+        VerifiedBonusSet {
+            bonus: Perbill,
+        },
+        /// `who`'s stakes started or stopped getting `VerifiedBonus`.
+        VerificationApplied {
+            who: T::AccountId,
+            verified: bool,
+        },
+        /// `DistributionSource` couldn't reserve `amount` to share among stakes, e.g. because
+        /// it's locked. It's tried again at the next distribution.
+        DistributionFailed {
+            amount: BalanceOf<T>,
+        },
     }
 
     #[pallet::error]
@@ -78,81 +422,166 @@ pub mod pallet {
         NotEnoughUnlockedStake,
         UnknownLockPeriod,
         AmountBelowMinimum,
+        EarlyUnstakeNotAllowed,
+        NoStakeUnlockingAt,
+        /// Stakes can only be added to if they have the same payer and controller.
+        StakeRolesMismatch,
+        NotController,
+        /// Rewards can only be compounded into stakes paid for by the account receiving them.
+        CannotCompoundDelegatedStake,
+        /// The stake's coin shares would overflow `TotalCoinShares`.
+        CoinSharesOverflow,
+        /// The stake already holds balance in `MaxTiersPerStake` other tiers.
+        TooManyTiers,
+        /// No longer returned, since stakes past `MaxMaturingPerBlock` are released at a later
+        /// distribution.
+        TooManyMaturing,
+        /// Unstaking would leave less staked than a `StakeLocks` lock requires.
+        StakeLocked,
+        /// The account already holds `MaxStakesPerAccount` stakes.
+        TooManyStakes,
     }
 
-    #[derive(Default, Decode, Encode, Debug, Clone)]
-    pub struct EncodableBigUint {
-        digits: Vec<u32>,
-    }
+    /// Upper bound on the number of tiers a single stake holds balance in. A stake only holds
+    /// more than one when stakes or roll-overs into different tiers unlock in the same block.
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MaxTiersPerStake;
 
-    impl From<BigUint> for EncodableBigUint {
-        fn from(n: BigUint) -> Self {
-            Self {
-                digits: n.to_u32_digits(),
-            }
+    impl Get<u32> for MaxTiersPerStake {
+        fn get() -> u32 {
+            4
         }
     }
 
-    impl Into<BigUint> for EncodableBigUint {
-        fn into(self) -> BigUint {
-            BigUint::new(self.digits)
-        }
+    /// Balance staked into one tier of `LockPeriodShares`.
+    #[derive(Default, Decode, Encode, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TierBalance<B> {
+        /// Shares per coin of the tier when the balance was staked into it.
+        pub shares: u32,
+        pub balance: B,
     }
 
-    #[derive(Default, Decode, Encode)]
+    /// A stake's balance in each tier it was staked into, ordered by shares per coin. Its coin
+    /// shares weight each balance by the shares per coin of its tier.
+    #[derive(Default, Decode, Encode, Debug, Clone, PartialEq, Eq)]
     pub struct ShareBalance<B> {
-        map: BTreeMap<u32, B>,
+        tiers: BoundedVec<TierBalance<B>, MaxTiersPerStake>,
     }
 
     impl<B> ShareBalance<B>
     where
-        B: Default + Copy + sp_arithmetic::traits::AtLeast32BitUnsigned,
+        B: Default + Copy + AtLeast32BitUnsigned,
     {
-        pub fn coin_shares(&self) -> BigUint
-        where
-            BigUint: Add<B, Output = BigUint> + From<B>,
-        {
-            self.map.iter().map(|(&s, &b)| BigUint::from(b) * s).sum()
+        /// A stake of `tiers`, if there are at most `MaxTiersPerStake` of them. Tiers with the
+        /// same shares per coin are merged.
+        pub fn from_tiers(tiers: impl IntoIterator<Item = TierBalance<B>>) -> Option<Self> {
+            let mut sb = Self::default();
+            for tier in tiers {
+                sb.increment(tier.shares, tier.balance).ok()?;
+            }
+
+            Some(sb)
+        }
+
+        pub fn tiers(&self) -> &[TierBalance<B>] {
+            &self.tiers
+        }
+
+        pub fn coin_shares(&self) -> u128 {
+            self.tiers.iter().fold(0u128, |acc, tier| {
+                let coin_shares = Self::coin_shares_of(tier.shares, tier.balance);
+                acc.saturating_add(coin_shares.unwrap_or(u128::MAX))
+            })
         }
 
         pub fn balance(&self) -> B {
-            self.map
-                .values()
-                .cloned()
-                .fold(B::default(), |acc, b| acc + b)
+            self.tiers
+                .iter()
+                .fold(B::default(), |acc, tier| acc + tier.balance)
+        }
+
+        /// Coin shares of `amount` staked at `shares` per coin, if they fit in a `u128`.
+        pub fn coin_shares_of(shares: u32, amount: B) -> Option<u128> {
+            UniqueSaturatedInto::<u128>::unique_saturated_into(amount).checked_mul(shares.into())
+        }
+
+        /// Whether balance can be added to the tier of `shares` per coin.
+        pub fn can_increment(&self, shares: u32) -> bool {
+            self.tiers.iter().any(|tier| tier.shares == shares)
+                || (self.tiers.len() as u32) < MaxTiersPerStake::get()
         }
 
-        fn distribute(&mut self, to_distribute: B, total_staked: &BigUint) -> B
-        where
-            BigUint: From<B>,
-            B: TryFrom<BigUint>,
-        {
-            let mut total = B::default();
+        /// Adds `amount` to the tier of `shares` per coin, unless `can_increment` doesn't allow
+        /// it.
+        fn increment(&mut self, shares: u32, amount: B) -> Result<(), ()> {
+            let mut tiers = self.tiers.to_vec();
+            match tiers.binary_search_by_key(&shares, |tier| tier.shares) {
+                Ok(index) => tiers[index].balance += amount,
+                Err(index) => tiers.insert(
+                    index,
+                    TierBalance {
+                        shares,
+                        balance: amount,
+                    },
+                ),
+            }
+            self.tiers = BoundedVec::try_from(tiers)?;
+
+            Ok(())
+        }
 
-            for (&shares, b) in self.map.iter_mut() {
-                let big_amount =
-                    BigUint::from(to_distribute) * BigUint::from(*b) * shares / total_staked;
-                let this_amount = B::try_from(big_amount)
-                    .ok() // Silence error since it's not Debug
-                    .expect("should never distribute more than B can handle");
+        /// Removes up to `amount`, starting from the tier with the most shares per coin. Returns
+        /// the amount and coin shares removed.
+        fn slash(&mut self, amount: B) -> (B, u128) {
+            let before = self.coin_shares();
+            let mut tiers = self.tiers.to_vec();
 
-                *b += this_amount;
-                total += this_amount;
+            let mut slashed = B::default();
+            for tier in tiers.iter_mut().rev() {
+                let this_tier = core::cmp::min(amount - slashed, tier.balance);
+                tier.balance -= this_tier;
+                slashed += this_tier;
+            }
+            tiers.retain(|tier| !tier.balance.is_zero());
+            // Never more tiers than there were.
+            if let Ok(tiers) = BoundedVec::try_from(tiers) {
+                self.tiers = tiers;
             }
 
-            total
+            (slashed, before.saturating_sub(self.coin_shares()))
         }
 
-        fn increment(&mut self, shares: u32, amount: B) {
-            *self.map.entry(shares).or_default() += amount;
+        /// Adds `amount` earned by the stake to its tiers, in proportion to their coin shares.
+        /// What rounding leaves over goes to the tier with the most shares per coin.
+        fn compound(&mut self, amount: B) {
+            let total = self.coin_shares();
+            let mut tiers = self.tiers.to_vec();
+
+            let mut added = B::default();
+            for tier in tiers.iter_mut() {
+                let coin_shares = Self::coin_shares_of(tier.shares, tier.balance);
+                let this_tier: u128 = multiply_by_rational(
+                    amount.unique_saturated_into(),
+                    coin_shares.unwrap_or(u128::MAX),
+                    total,
+                )
+                .unwrap_or_default();
+                let this_tier = B::unique_saturated_from(this_tier);
+                tier.balance += this_tier;
+                added += this_tier;
+            }
+            if let Some(last) = tiers.last_mut() {
+                last.balance += amount - added;
+            }
+            // Never more tiers than there were.
+            if let Ok(tiers) = BoundedVec::try_from(tiers) {
+                self.tiers = tiers;
+            }
         }
     }
 
     #[pallet::call]
-    impl<T: Config> Pallet<T>
-    where
-        BigUint: From<BalanceOf<T>>,
-    {
+    impl<T: Config> Pallet<T> {
         #[pallet::weight((
             10_000 + T::DbWeight::get().reads_writes(0, 1),
             DispatchClass::Normal,
@@ -166,11 +595,13 @@ pub mod pallet {
 
             MinimumStake::<T>::set(min);
 
+            Self::deposit_event(Event::<T>::MinimumStakeSet { min });
+
             Ok(())
         }
 
         #[pallet::weight((
-            10_000 + T::DbWeight::get().reads_writes(0, 1),
+            10_000 + T::DbWeight::get().reads_writes(1, 1),
             DispatchClass::Normal,
             Pays::No
         ))]
@@ -181,12 +612,65 @@ pub mod pallet {
         ) -> DispatchResult {
             ensure_root(origin)?;
 
-            LockPeriodShares::<T>::insert(blocks, shares);
+            // Existing stakes, and so `TotalCoinShares`, are unaffected.
+            let previous = LockPeriodShares::<T>::mutate(blocks, |s| s.replace(shares));
+
+            Self::deposit_event(Event::<T>::LockPeriodSharesSet {
+                lock_period: blocks,
+                shares,
+                previous,
+            });
+
+            Ok(())
+        }
+
+        /// Stops new stakes and roll-overs into the tier locked for `blocks`. Stakes already in
+        /// it run until they mature.
+        #[pallet::weight((
+            10_000 + T::DbWeight::get().reads_writes(1, 1),
+            DispatchClass::Normal,
+            Pays::No
+        ))]
+        pub fn retire_lock_period(
+            origin: OriginFor<T>,
+            #[pallet::compact] blocks: BlockNumberFor<T>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            let shares = LockPeriodShares::<T>::take(blocks).ok_or(Error::<T>::UnknownLockPeriod)?;
+
+            Self::deposit_event(Event::<T>::LockPeriodRetired {
+                lock_period: blocks,
+                shares,
+            });
 
             Ok(())
         }
 
-        #[pallet::weight(10_000 + T::DbWeight::get().reads_writes(4, 2))]
+        #[pallet::weight((
+            10_000 + T::DbWeight::get().reads_writes(0, 1),
+            DispatchClass::Normal,
+            Pays::No
+        ))]
+        pub fn set_early_unstake_penalty(
+            origin: OriginFor<T>,
+            penalty: Option<Perbill>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            EarlyUnstakePenalty::<T>::set(penalty);
+
+            Self::deposit_event(Event::<T>::EarlyUnstakePenaltySet { penalty });
+
+            Ok(())
+        }
+
+        #[pallet::weight(
+            10_000
+                + T::DbWeight::get().reads_writes(10, 7)
+                + T::DbWeight::get().reads(T::MaxStakesPerAccount::get() as Weight)
+                + Pallet::<T>::apply_bonus_weight()
+        )]
         pub fn stake(
             origin: OriginFor<T>,
             #[pallet::compact] lock_period: BlockNumberFor<T>,
@@ -194,6 +678,440 @@ pub mod pallet {
         ) -> DispatchResult {
             let address = ensure_signed(origin)?;
 
+            Self::do_stake(address.clone(), address.clone(), address, lock_period, amount)
+        }
+
+        /// Stakes `amount` of the caller's balance for `beneficiary`, who receives its rewards.
+        /// Only `controller` can unstake it early or set its roll-over, and it's returned to the
+        /// caller when it's released.
+        #[pallet::weight(
+            10_000
+                + T::DbWeight::get().reads_writes(10, 8)
+                + T::DbWeight::get().reads(T::MaxStakesPerAccount::get() as Weight)
+                + Pallet::<T>::apply_bonus_weight()
+        )]
+        pub fn stake_for(
+            origin: OriginFor<T>,
+            beneficiary: T::AccountId,
+            controller: T::AccountId,
+            #[pallet::compact] lock_period: BlockNumberFor<T>,
+            #[pallet::compact] amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            let payer = ensure_signed(origin)?;
+
+            Self::do_stake(payer, beneficiary, controller, lock_period, amount)
+        }
+
+        /// Sets what happens to `beneficiary`'s stake unlocking at `unstake_at` when it matures.
+        /// `None` returns it, which is the default.
+        #[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 1))]
+        pub fn set_roll_over(
+            origin: OriginFor<T>,
+            beneficiary: T::AccountId,
+            #[pallet::compact] unstake_at: BlockNumberFor<T>,
+            roll_over: Option<RollOver<BlockNumberFor<T>>>,
+        ) -> DispatchResult {
+            let address = ensure_signed(origin)?;
+
+            ensure!(
+                StakedAmounts::<T>::contains_key(&beneficiary, unstake_at),
+                Error::<T>::NoStakeUnlockingAt
+            );
+            let roles = Self::roles(&beneficiary, unstake_at);
+            ensure!(roles.controller == address, Error::<T>::NotController);
+
+            match &roll_over {
+                Some(roll_over) => {
+                    ensure!(
+                        !roll_over.lock_period.is_zero()
+                            && LockPeriodShares::<T>::contains_key(roll_over.lock_period),
+                        Error::<T>::UnknownLockPeriod
+                    );
+                    ensure!(
+                        !roll_over.compound || roles.payer == beneficiary,
+                        Error::<T>::CannotCompoundDelegatedStake
+                    );
+                    RollOvers::<T>::insert(&beneficiary, unstake_at, roll_over);
+                }
+                None => RollOvers::<T>::remove(&beneficiary, unstake_at),
+            }
+
+            Self::deposit_event(Event::<T>::RollOverSet {
+                who: beneficiary,
+                unstake_at,
+                roll_over,
+            });
+
+            Ok(())
+        }
+
+        /// Releases `beneficiary`'s stake unlocking at `unstake_at` before it matures, giving up
+        /// `EarlyUnstakePenalty` of it to `EarlyUnstakePenaltyHandler`. Matured stakes that have
+        /// not been released yet are returned in full. Stakes that `StakeLocks` relies on can't
+        /// be unstaked early.
+        #[pallet::weight(10_000 + T::DbWeight::get().reads_writes(10, 8))]
+        pub fn unstake_early(
+            origin: OriginFor<T>,
+            beneficiary: T::AccountId,
+            #[pallet::compact] unstake_at: BlockNumberFor<T>,
+        ) -> DispatchResult {
+            let address = ensure_signed(origin)?;
+
+            let sb = StakedAmounts::<T>::get(&beneficiary, unstake_at);
+            let amount = sb.balance();
+            ensure!(!amount.is_zero(), Error::<T>::NoStakeUnlockingAt);
+            let roles = Self::roles(&beneficiary, unstake_at);
+            ensure!(roles.controller == address, Error::<T>::NotController);
+
+            let now = <frame_system::Pallet<T>>::block_number();
+            let penalty_rate = if unstake_at > now {
+                EarlyUnstakePenalty::<T>::get().ok_or(Error::<T>::EarlyUnstakeNotAllowed)?
+            } else {
+                Perbill::zero()
+            };
+            if let Some((until, locked)) = T::StakeLocks::stake_lock(&beneficiary) {
+                // Only stakes unlocking at or after `until` count towards the lock.
+                if now < until && unstake_at >= until {
+                    let staked = <Self as crate::StakeInspector<_, _, _>>::staked_until(
+                        &beneficiary,
+                        until,
+                    );
+                    ensure!(staked.saturating_sub(amount) >= locked, Error::<T>::StakeLocked);
+                }
+            }
+
+            // Rewards already earned are paid in full, only the stake itself is penalized.
+            Self::pay_rewards(&beneficiary, unstake_at, &sb, BalanceStatus::Free);
+
+            let penalty = penalty_rate * amount;
+            T::Currency::unreserve(&roles.payer, amount - penalty);
+            let (imbalance, _) = T::Currency::slash_reserved(&roles.payer, penalty);
+            T::EarlyUnstakePenaltyHandler::on_unbalanced(imbalance);
+
+            Self::remove_stake(&beneficiary, unstake_at, &sb);
+            Self::unschedule_release(&beneficiary, unstake_at);
+
+            Self::deposit_event(Event::<T>::UnstakedEarly {
+                amount: amount - penalty,
+                penalty,
+                who: beneficiary,
+            });
+
+            Ok(())
+        }
+
+        /// Sets the bonus for verified accounts. It applies to their existing stakes from the next
+        /// distribution.
+        #[pallet::weight((
+            10_000 + T::DbWeight::get().reads_writes(0, 1),
+            DispatchClass::Normal,
+            Pays::No
+        ))]
+        pub fn set_verified_bonus(origin: OriginFor<T>, bonus: Perbill) -> DispatchResult {
+            ensure_root(origin)?;
+
+            VerifiedBonus::<T>::set(bonus);
+
+            Self::deposit_event(Event::<T>::VerifiedBonusSet { bonus });
+
+            Ok(())
+        }
+
+        /// Brings whether `who`'s stakes get `VerifiedBonus` up to date with their verification,
+        /// for changes that staking isn't told about.
+        #[pallet::weight(10_000 + Pallet::<T>::apply_bonus_weight())]
+        pub fn apply_bonus(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            Self::do_apply_bonus(&who);
+
+            Ok(())
+        }
+
+        /// Pays the caller the rewards their stake unlocking at `unstake_at` has earned so far.
+        /// Rewards of stakes the caller paid for are added to the stake, the others to their free
+        /// balance.
+        #[pallet::weight(10_000 + T::DbWeight::get().reads_writes(9, 6))]
+        pub fn claim_rewards(
+            origin: OriginFor<T>,
+            #[pallet::compact] unstake_at: BlockNumberFor<T>,
+        ) -> DispatchResult {
+            let address = ensure_signed(origin)?;
+
+            ensure!(
+                StakedAmounts::<T>::contains_key(&address, unstake_at),
+                Error::<T>::NoStakeUnlockingAt
+            );
+            StakedAmounts::<T>::mutate(&address, unstake_at, |sb| {
+                Self::realise_rewards(&address, unstake_at, sb)
+            });
+
+            Ok(())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Rewards the stake unlocking at `unstake_at` has earned since they were last paid, up to
+        /// what `DistributionSource` has reserved to pay them.
+        pub fn pending_rewards(who: &T::AccountId, unstake_at: BlockNumberFor<T>) -> BalanceOf<T> {
+            let owed = Self::rewards_owed(
+                StakedAmounts::<T>::get(who, unstake_at).coin_shares(),
+                RewardCheckpoints::<T>::get(who, unstake_at),
+                Self::reward_per_coin_share(who),
+            );
+
+            core::cmp::min(owed, T::Currency::reserved_balance(&T::DistributionSource::get()))
+        }
+
+        /// Tiers that can currently be staked into, as lock periods and their shares per coin.
+        pub fn lock_period_tiers() -> Vec<(BlockNumberFor<T>, u32)> {
+            let mut tiers = LockPeriodShares::<T>::iter().collect::<Vec<_>>();
+            tiers.sort();
+            tiers
+        }
+
+        /// `who`'s stakes and rewards, with an estimate of their next reward.
+        pub fn staking_summary(
+            who: &T::AccountId,
+        ) -> StakingSummary<BlockNumberFor<T>, BalanceOf<T>> {
+            let mut positions = Vec::new();
+            let mut pending_rewards = BalanceOf::<T>::default();
+            let mut coin_shares = 0u128;
+
+            for (unstake_at, sb) in StakedAmounts::<T>::iter_prefix(who) {
+                let roll_over = RollOvers::<T>::get(who, unstake_at);
+                positions.extend(sb.tiers().iter().map(|tier| StakePosition {
+                    unstake_at,
+                    shares: tier.shares,
+                    balance: tier.balance,
+                    roll_over: roll_over.clone(),
+                }));
+
+                pending_rewards += Self::pending_rewards(who, unstake_at);
+                coin_shares = coin_shares.saturating_add(Self::effective_coin_shares(who, &sb));
+            }
+
+            let total = Self::effective_total_coin_shares();
+            let (coin_shares_fraction, estimated_next_reward) = if total.is_zero() {
+                (Perbill::zero(), BalanceOf::<T>::default())
+            } else {
+                let coin_shares = core::cmp::min(coin_shares, total);
+                let to_distribute = T::Currency::free_balance(&T::DistributionSource::get());
+                let reward =
+                    multiply_by_rational(to_distribute.unique_saturated_into(), coin_shares, total)
+                        .unwrap_or_default();
+
+                (
+                    Perbill::from_rational_approximation(coin_shares, total),
+                    BalanceOf::<T>::unique_saturated_from(reward),
+                )
+            };
+
+            StakingSummary {
+                positions,
+                pending_rewards,
+                coin_shares_fraction,
+                estimated_next_reward,
+            }
+        }
+
+        /// Coin shares `sb` currently earns rewards with, including any bonus.
+        pub fn effective_coin_shares(
+            who: &T::AccountId,
+            sb: &ShareBalance<BalanceOf<T>>,
+        ) -> u128 {
+            if VerifiedStakers::<T>::contains_key(who) {
+                Self::with_bonus(VerifiedBonus::<T>::get(), sb.coin_shares())
+            } else {
+                sb.coin_shares()
+            }
+        }
+
+        /// Coin shares of every stake, including bonuses, which distributions are shared among.
+        pub fn effective_total_coin_shares() -> u128 {
+            TotalCoinShares::<T>::get()
+                .saturating_add(VerifiedBonus::<T>::get() * VerifiedCoinShares::<T>::get())
+        }
+
+        fn with_bonus(bonus: Perbill, coin_shares: u128) -> u128 {
+            coin_shares.saturating_add(bonus * coin_shares)
+        }
+
+        /// The accumulator `who`'s stakes earn rewards by.
+        fn reward_per_coin_share(who: &T::AccountId) -> FixedU128 {
+            if VerifiedStakers::<T>::contains_key(who) {
+                RewardPerVerifiedCoinShare::<T>::get()
+            } else {
+                RewardPerCoinShare::<T>::get()
+            }
+        }
+
+        /// Moves `who`'s stakes to or from `VerifiedStakers`, if their verification changed.
+        /// Returns the weight used.
+        /// Upper bound on the weight of `do_apply_bonus`, for an account with
+        /// `MaxStakesPerAccount` stakes.
+        pub(crate) fn apply_bonus_weight() -> Weight {
+            T::DbWeight::get().reads_writes(8, 4).saturating_add(
+                T::DbWeight::get()
+                    .reads_writes(8, 6)
+                    .saturating_mul(T::MaxStakesPerAccount::get() as Weight),
+            )
+        }
+
+        pub(crate) fn do_apply_bonus(who: &T::AccountId) -> Weight {
+            let mut weight = T::DbWeight::get().reads_writes(8, 4);
+            let verified = T::Verification::is_verified(who);
+            if verified == VerifiedStakers::<T>::contains_key(who) {
+                return weight;
+            }
+
+            let reward_per_coin_share = if verified {
+                RewardPerVerifiedCoinShare::<T>::get()
+            } else {
+                RewardPerCoinShare::<T>::get()
+            };
+            let mut coin_shares = 0u128;
+            let stakes = StakedAmounts::<T>::iter_prefix(who).collect::<Vec<_>>();
+            // Realising each stake's rewards pays them and updates its coin shares.
+            weight += T::DbWeight::get().reads_writes(8, 6) * stakes.len() as Weight;
+            for (unstake_at, mut sb) in stakes {
+                // Rewards so far were earned with or without the bonus, as it was.
+                Self::realise_rewards(who, unstake_at, &mut sb);
+                RewardCheckpoints::<T>::insert(who, unstake_at, reward_per_coin_share);
+                StakedAmounts::<T>::insert(who, unstake_at, &sb);
+
+                coin_shares = coin_shares.saturating_add(sb.coin_shares());
+            }
+
+            if verified {
+                VerifiedStakers::<T>::insert(who, ());
+                VerifiedCoinShares::<T>::mutate(|v| *v = v.saturating_add(coin_shares));
+            } else {
+                VerifiedStakers::<T>::remove(who);
+                VerifiedCoinShares::<T>::mutate(|v| *v = v.saturating_sub(coin_shares));
+            }
+
+            Self::deposit_event(Event::<T>::VerificationApplied {
+                who: who.clone(),
+                verified,
+            });
+
+            weight
+        }
+
+        fn rewards_owed(
+            coin_shares: u128,
+            checkpoint: FixedU128,
+            reward_per_coin_share: FixedU128,
+        ) -> BalanceOf<T> {
+            let owed = reward_per_coin_share
+                .saturating_sub(checkpoint)
+                .saturating_mul_int(coin_shares);
+
+            BalanceOf::<T>::unique_saturated_from(owed)
+        }
+
+        /// Pays `who` the rewards `sb` has earned since they were last paid, into their balance of
+        /// kind `status`. Returns the amount paid.
+        fn pay_rewards(
+            who: &T::AccountId,
+            unstake_at: BlockNumberFor<T>,
+            sb: &ShareBalance<BalanceOf<T>>,
+            status: BalanceStatus,
+        ) -> BalanceOf<T> {
+            let reward_per_coin_share = Self::reward_per_coin_share(who);
+            let checkpoint = RewardCheckpoints::<T>::get(who, unstake_at);
+            let owed = Self::rewards_owed(sb.coin_shares(), checkpoint, reward_per_coin_share);
+            RewardCheckpoints::<T>::insert(who, unstake_at, reward_per_coin_share);
+
+            if owed.is_zero() {
+                return owed;
+            }
+
+            // Rounding up `RewardPerCoinShare` can leave the last staker owed slightly more than
+            // what's reserved, in which case they get what's left.
+            let paid = match T::Currency::repatriate_reserved(
+                &T::DistributionSource::get(),
+                who,
+                owed,
+                status,
+            ) {
+                Ok(not_moved) => owed - not_moved,
+                Err(_) => BalanceOf::<T>::default(),
+            };
+
+            if !paid.is_zero() {
+                Self::deposit_event(Event::<T>::RewardsPaid {
+                    amount: paid,
+                    who: who.clone(),
+                    unstake_at,
+                });
+            }
+
+            paid
+        }
+
+        /// Pays `who` the rewards `sb` has earned, compounding them into `sb` if `who` paid for
+        /// the stake themselves. The caller stores `sb`. Returns the amount paid.
+        fn realise_rewards(
+            who: &T::AccountId,
+            unstake_at: BlockNumberFor<T>,
+            sb: &mut ShareBalance<BalanceOf<T>>,
+        ) -> BalanceOf<T> {
+            // Delegated stakes are reserved from their payer, who doesn't earn their rewards.
+            if Self::roles(who, unstake_at).payer != *who {
+                return Self::pay_rewards(who, unstake_at, sb, BalanceStatus::Free);
+            }
+
+            let paid = Self::pay_rewards(who, unstake_at, sb, BalanceStatus::Reserved);
+            let before = sb.coin_shares();
+            sb.compound(paid);
+            Self::add_coin_shares(who, sb.coin_shares().saturating_sub(before));
+
+            paid
+        }
+
+        /// Adds `to_distribute` to the rewards of every current stake, in proportion to its coin
+        /// shares.
+        fn distribute(to_distribute: BalanceOf<T>) -> BalanceOf<T> {
+            let total_staked = Self::effective_total_coin_shares();
+            if total_staked.is_zero() || to_distribute.is_zero() {
+                return BalanceOf::<T>::default();
+            }
+
+            if T::Currency::reserve(&T::DistributionSource::get(), to_distribute).is_err() {
+                Self::deposit_event(Event::<T>::DistributionFailed {
+                    amount: to_distribute,
+                });
+                return BalanceOf::<T>::default();
+            }
+
+            // Rounded up so that stakes owed a whole number of units get all of them.
+            let increment = FixedU128::checked_from_rational(
+                UniqueSaturatedInto::<u128>::unique_saturated_into(to_distribute),
+                total_staked,
+            )
+            .map_or_else(FixedU128::max_value, |i| {
+                i.saturating_add(FixedU128::from_inner(1))
+            });
+            RewardPerCoinShare::<T>::mutate(|r| *r = r.saturating_add(increment));
+            let verified_increment = FixedU128::from_inner(
+                increment
+                    .into_inner()
+                    .saturating_add(VerifiedBonus::<T>::get() * increment.into_inner()),
+            );
+            RewardPerVerifiedCoinShare::<T>::mutate(|r| *r = r.saturating_add(verified_increment));
+
+            to_distribute
+        }
+
+        fn do_stake(
+            payer: T::AccountId,
+            beneficiary: T::AccountId,
+            controller: T::AccountId,
+            lock_period: BlockNumberFor<T>,
+            amount: BalanceOf<T>,
+        ) -> DispatchResult {
             if let Some(min) = MinimumStake::<T>::get() {
                 if amount < min {
                     return Err(Error::<T>::AmountBelowMinimum.into());
@@ -202,73 +1120,354 @@ pub mod pallet {
 
             let shares =
                 LockPeriodShares::<T>::get(lock_period).ok_or(Error::<T>::UnknownLockPeriod)?;
+            ensure!(
+                ShareBalance::coin_shares_of(shares, amount)
+                    .and_then(|c| c.checked_add(TotalCoinShares::<T>::get()))
+                    .is_some(),
+                Error::<T>::CoinSharesOverflow
+            );
+
+            let unstake_at = <frame_system::Pallet<T>>::block_number() + lock_period;
+            if !StakedAmounts::<T>::contains_key(&beneficiary, unstake_at) {
+                let stakes = StakedAmounts::<T>::iter_prefix(&beneficiary).count() as u32;
+                ensure!(
+                    stakes < T::MaxStakesPerAccount::get(),
+                    Error::<T>::TooManyStakes
+                );
+            }
+            let roles = StakeRoles { payer, controller };
+            Self::ensure_can_add_to(&beneficiary, unstake_at, &roles, shares)?;
 
-            T::Currency::reserve(&address, amount)
+            T::Currency::reserve(&roles.payer, amount)
                 .map_err(|_| Error::<T>::CannotStakeMoreThanBalance)?;
 
-            StakedAmounts::<T>::mutate(
-                address,
-                <frame_system::Pallet<T>>::block_number() + lock_period,
-                |sb| sb.increment(shares, amount),
-            );
-            TotalCoinShares::<T>::mutate(|b| {
-                let current_value: BigUint = b.clone().into();
-                let increment = BigUint::from(amount) * shares;
-                *b = EncodableBigUint::from(current_value + increment)
+            Self::do_apply_bonus(&beneficiary);
+            let coin_shares = Self::add_stake(&beneficiary, unstake_at, &roles, shares, amount);
+
+            Self::deposit_event(Event::<T>::Staked {
+                amount,
+                who: beneficiary,
+                unstake_at,
+                payer: roles.payer,
+                controller: roles.controller,
+                coin_shares,
             });
 
             Ok(())
         }
-    }
 
-    #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T>
-    where
-        BigUint: From<BalanceOf<T>> + Add<BalanceOf<T>, Output = BigUint>,
-        BalanceOf<T>: TryFrom<BigUint>,
-    {
-        fn on_finalize(current_block: BlockNumberFor<T>) {
-            if current_block % T::DistributeEveryNBlocks::get() != 0u32.into() {
+        /// Payer and controller of `who`'s stake unlocking at `unstake_at`.
+        pub fn roles(
+            who: &T::AccountId,
+            unstake_at: BlockNumberFor<T>,
+        ) -> StakeRoles<T::AccountId> {
+            DelegatedStakes::<T>::get(who, unstake_at).unwrap_or_else(|| StakeRoles {
+                payer: who.clone(),
+                controller: who.clone(),
+            })
+        }
+
+        /// Whether a stake with `roles` can be added to `who`'s stake unlocking at `unstake_at`, in
+        /// the tier of `shares` per coin.
+        fn ensure_can_add_to(
+            who: &T::AccountId,
+            unstake_at: BlockNumberFor<T>,
+            roles: &StakeRoles<T::AccountId>,
+            shares: u32,
+        ) -> Result<(), Error<T>> {
+            if StakedAmounts::<T>::contains_key(who, unstake_at) {
+                ensure!(
+                    Self::roles(who, unstake_at) == *roles,
+                    Error::<T>::StakeRolesMismatch
+                );
+                ensure!(
+                    StakedAmounts::<T>::get(who, unstake_at).can_increment(shares),
+                    Error::<T>::TooManyTiers
+                );
+            }
+
+            Ok(())
+        }
+
+        /// Adds `amount`, which must already be reserved from `roles.payer`, to `who`'s stake
+        /// unlocking at `unstake_at`. `ensure_can_add_to` must allow it. Returns the coin shares
+        /// added, including any bonus.
+        fn add_stake(
+            who: &T::AccountId,
+            unstake_at: BlockNumberFor<T>,
+            roles: &StakeRoles<T::AccountId>,
+            shares: u32,
+            amount: BalanceOf<T>,
+        ) -> u128 {
+            if !StakedAmounts::<T>::contains_key(who, unstake_at) {
+                Self::schedule_release(who, unstake_at);
+
+                if roles.payer != *who || roles.controller != *who {
+                    DelegatedStakes::<T>::insert(who, unstake_at, roles);
+                }
+            }
+
+            // Rewards are paid before the stake grows, since they were earned by its old size.
+            let added = StakedAmounts::<T>::mutate(who, unstake_at, |sb| {
+                Self::realise_rewards(who, unstake_at, sb);
+
+                let before = sb.coin_shares();
+                let _ = sb.increment(shares, amount);
+                sb.coin_shares().saturating_sub(before)
+            });
+            Self::add_coin_shares(who, added);
+
+            if VerifiedStakers::<T>::contains_key(who) {
+                Self::with_bonus(VerifiedBonus::<T>::get(), added)
+            } else {
+                added
+            }
+        }
+
+        /// Pays the rewards of a matured stake and returns it to its owner, or restakes it if it
+        /// has a `RollOver` into a known tier.
+        fn release(who: &T::AccountId, unstake_at: BlockNumberFor<T>, now: BlockNumberFor<T>) {
+            let sb = StakedAmounts::<T>::get(who, unstake_at);
+            // Stakes unstaked early are released without waiting for their release block.
+            if sb.balance().is_zero() {
                 return;
             }
 
-            let to_distribute = T::Currency::free_balance(&T::DistributionSource::get());
-            let total_staked = TotalCoinShares::<T>::get().into();
+            let roles = Self::roles(who, unstake_at);
+            let roll_over = RollOvers::<T>::get(who, unstake_at)
+                .and_then(|roll_over| {
+                    LockPeriodShares::<T>::get(roll_over.lock_period)
+                        .map(|shares| (roll_over, shares))
+                })
+                .filter(|(roll_over, shares)| {
+                    Self::ensure_can_add_to(who, now + roll_over.lock_period, &roles, *shares)
+                        .is_ok()
+                });
+            if let Some((roll_over, shares)) = roll_over {
+                return Self::roll_over(who, unstake_at, sb, roles, roll_over, shares, now);
+            }
 
-            let mut new_coin_shares = BigUint::default();
+            let rewards = Self::pay_rewards(who, unstake_at, &sb, BalanceStatus::Free);
+            T::Currency::unreserve(&roles.payer, sb.balance());
+            Self::remove_stake(who, unstake_at, &sb);
 
-            StakedAmounts::<T>::translate(|addr, unstake_at, mut sb: ShareBalance<_>| {
-                let amt_to_this = sb.distribute(to_distribute, &total_staked);
+            Self::deposit_event(Event::<T>::Unstaked {
+                amount: sb.balance() + rewards,
+                who: who.clone(),
+            });
+        }
 
-                T::Currency::transfer(
-                    &T::DistributionSource::get(),
-                    &addr,
-                    amt_to_this,
-                    ExistenceRequirement::AllowDeath,
-                )
-                .expect("distributing based on balance");
+        fn roll_over(
+            who: &T::AccountId,
+            unstake_at: BlockNumberFor<T>,
+            sb: ShareBalance<BalanceOf<T>>,
+            roles: StakeRoles<T::AccountId>,
+            roll_over: RollOver<BlockNumberFor<T>>,
+            shares: u32,
+            now: BlockNumberFor<T>,
+        ) {
+            let amount = if roll_over.compound && roles.payer == *who {
+                sb.balance() + Self::pay_rewards(who, unstake_at, &sb, BalanceStatus::Reserved)
+            } else {
+                Self::pay_rewards(who, unstake_at, &sb, BalanceStatus::Free);
+                sb.balance()
+            };
+            Self::remove_stake(who, unstake_at, &sb);
 
-                if unstake_at <= current_block {
-                    T::Currency::unreserve(&addr, sb.balance() - amt_to_this);
+            let new_unstake_at = now + roll_over.lock_period;
+            Self::add_stake(who, new_unstake_at, &roles, shares, amount);
+            RollOvers::<T>::insert(who, new_unstake_at, roll_over);
 
-                    Self::deposit_event(Event::<T>::Unstaked {
-                        amount: sb.balance(),
-                        who: addr,
-                    });
-                    return None;
+            Self::deposit_event(Event::<T>::RolledOver {
+                amount,
+                who: who.clone(),
+                unstake_at: new_unstake_at,
+            });
+        }
+
+        fn remove_stake(
+            who: &T::AccountId,
+            unstake_at: BlockNumberFor<T>,
+            sb: &ShareBalance<BalanceOf<T>>,
+        ) {
+            StakedAmounts::<T>::remove(who, unstake_at);
+            RewardCheckpoints::<T>::remove(who, unstake_at);
+            RollOvers::<T>::remove(who, unstake_at);
+            DelegatedStakes::<T>::remove(who, unstake_at);
+            Self::remove_coin_shares(who, sb.coin_shares());
+        }
+
+        /// Indexes `who`'s new stake unlocking at `unstake_at` in `Maturing`, at the first
+        /// distribution from its release block that has room for it. Full release blocks only
+        /// delay when stakes are released, so they can't keep anyone from staking.
+        pub(crate) fn schedule_release(who: &T::AccountId, unstake_at: BlockNumberFor<T>) {
+            let mut release_block = Self::release_block(unstake_at);
+            while Maturing::<T>::try_mutate(release_block, |maturing| {
+                maturing.try_push((who.clone(), unstake_at))
+            })
+            .is_err()
+            {
+                release_block += T::DistributeEveryNBlocks::get();
+            }
+        }
+
+        /// Removes `who`'s stake unlocking at `unstake_at` from `Maturing`, for stakes that are
+        /// released before their release block. Stakes are only scheduled past full release
+        /// blocks, so the search stops at the first one with room.
+        fn unschedule_release(who: &T::AccountId, unstake_at: BlockNumberFor<T>) {
+            let stake = (who.clone(), unstake_at);
+            let mut release_block = Self::release_block(unstake_at);
+            loop {
+                let mut maturing = Maturing::<T>::get(release_block);
+                if let Some(index) = maturing.iter().position(|s| *s == stake) {
+                    maturing.remove(index);
+                    return Maturing::<T>::insert(release_block, maturing);
+                }
+                if (maturing.len() as u32) < T::MaxMaturingPerBlock::get() {
+                    return;
+                }
+
+                release_block += T::DistributeEveryNBlocks::get();
+            }
+        }
+
+        fn add_coin_shares(who: &T::AccountId, added: u128) {
+            TotalCoinShares::<T>::mutate(|total| *total = total.saturating_add(added));
+            if VerifiedStakers::<T>::contains_key(who) {
+                VerifiedCoinShares::<T>::mutate(|v| *v = v.saturating_add(added));
+            }
+        }
+
+        fn remove_coin_shares(who: &T::AccountId, removed: u128) {
+            TotalCoinShares::<T>::mutate(|total| *total = total.saturating_sub(removed));
+            if VerifiedStakers::<T>::contains_key(who) {
+                VerifiedCoinShares::<T>::mutate(|v| *v = v.saturating_sub(removed));
+            }
+        }
+
+        /// The first distribution block at or after `unstake_at`, when the stake is released.
+        pub(crate) fn release_block(unstake_at: BlockNumberFor<T>) -> BlockNumberFor<T> {
+            let every_n = T::DistributeEveryNBlocks::get();
+            let past_distribution = unstake_at % every_n;
+
+            if past_distribution.is_zero() {
+                unstake_at
+            } else {
+                unstake_at + every_n - past_distribution
+            }
+        }
+    }
+
+    impl<T: Config> crate::StakeInspector<T::AccountId, BlockNumberFor<T>, BalanceOf<T>>
+        for Pallet<T>
+    {
+        fn staked_until(who: &T::AccountId, until: BlockNumberFor<T>) -> BalanceOf<T> {
+            StakedAmounts::<T>::iter_prefix(who)
+                .filter(|(unstake_at, _)| *unstake_at >= until)
+                .fold(BalanceOf::<T>::default(), |acc, (_, sb)| acc + sb.balance())
+        }
+
+        fn slash(
+            who: &T::AccountId,
+            amount: BalanceOf<T>,
+            beneficiary: &T::AccountId,
+        ) -> BalanceOf<T> {
+            let stakes = StakedAmounts::<T>::iter_prefix(who).collect::<Vec<_>>();
+            // Rewards were earned by the stake before it was slashed.
+            for (unstake_at, sb) in &stakes {
+                Self::pay_rewards(who, *unstake_at, sb, BalanceStatus::Free);
+            }
+            let mut slashed = BalanceOf::<T>::default();
+            let mut removed_shares = 0u128;
+            for (unstake_at, mut sb) in stakes {
+                if slashed >= amount {
+                    break;
                 }
 
-                T::Currency::reserve(&addr, amt_to_this).expect("just deposited to this account");
-                new_coin_shares += sb.coin_shares();
+                // Delegated stakes are slashed from whoever has them reserved.
+                let payer = Self::roles(who, unstake_at).payer;
+                let to_slash = core::cmp::min(amount - slashed, sb.balance());
+                let moved = match T::Currency::repatriate_reserved(
+                    &payer,
+                    beneficiary,
+                    to_slash,
+                    BalanceStatus::Free,
+                ) {
+                    Ok(not_moved) => to_slash - not_moved,
+                    Err(_) => continue,
+                };
 
-                Some(sb)
+                let (this_amount, removed) = sb.slash(moved);
+                slashed += this_amount;
+                removed_shares = removed_shares.saturating_add(removed);
+
+                if sb.balance().is_zero() {
+                    StakedAmounts::<T>::remove(who, unstake_at);
+                    RewardCheckpoints::<T>::remove(who, unstake_at);
+                    RollOvers::<T>::remove(who, unstake_at);
+                    DelegatedStakes::<T>::remove(who, unstake_at);
+                } else {
+                    StakedAmounts::<T>::insert(who, unstake_at, sb);
+                }
+            }
+            Self::remove_coin_shares(who, removed_shares);
+
+            Self::deposit_event(Event::<T>::Slashed {
+                amount: slashed,
+                who: who.clone(),
+                beneficiary: beneficiary.clone(),
             });
 
-            TotalCoinShares::<T>::set(new_coin_shares.into());
+            slashed
+        }
+    }
+
+    impl<T: Config> crate::OnVerificationChanged<T::AccountId> for Pallet<T> {
+        fn on_verification_changed(who: &T::AccountId) -> Weight {
+            Self::do_apply_bonus(who)
+        }
+
+        fn on_verification_changed_weight() -> Weight {
+            Self::apply_bonus_weight()
+        }
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+            if now % T::DistributeEveryNBlocks::get() != 0u32.into() {
+                return 0;
+            }
+
+            // `on_finalize` distributes, then releases or rolls over each stake maturing now, of
+            // which there are at most `MaxMaturingPerBlock`.
+            let maturing = Maturing::<T>::decode_len(now).unwrap_or(0) as Weight;
+            let release = 10_000 + T::DbWeight::get().reads_writes(16, 14);
+
+            (10_000 + T::DbWeight::get().reads_writes(5, 4))
+                .saturating_add(release.saturating_mul(maturing))
+        }
+
+        fn on_finalize(current_block: BlockNumberFor<T>) {
+            if current_block % T::DistributeEveryNBlocks::get() != 0u32.into() {
+                return;
+            }
+
+            let to_distribute = T::Currency::free_balance(&T::DistributionSource::get());
+            let distributed = Self::distribute(to_distribute);
+
+            for (who, unstake_at) in Maturing::<T>::take(current_block).into_inner() {
+                Self::release(&who, unstake_at, current_block);
+            }
 
             Self::deposit_event(Event::<T>::Distribution {
-                amount: to_distribute - T::Currency::free_balance(&T::DistributionSource::get()),
+                amount: distributed,
+                reward_per_coin_share: RewardPerCoinShare::<T>::get(),
             });
         }
+
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::migrate::<T>()
+        }
     }
 }
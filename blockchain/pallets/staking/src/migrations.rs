@@ -0,0 +1,129 @@
+use crate::pallet::{Config, Maturing, Pallet, Releases, StakedAmounts, StorageVersion};
+use frame_support::{inherent::Vec, traits::Get, weights::Weight};
+
+pub fn migrate<T: Config>() -> Weight {
+    let mut weight = T::DbWeight::get().reads(1);
+    let version = StorageVersion::<T>::get();
+
+    // V3 only changes how values are encoded, so it goes first to let V2 read `StakedAmounts`.
+    if version < Releases::V3 {
+        weight = weight.saturating_add(v3::migrate::<T>());
+    }
+    if version < Releases::V2 {
+        weight = weight.saturating_add(v2::migrate::<T>());
+    }
+    if version < Releases::V3 {
+        StorageVersion::<T>::put(Releases::V3);
+    }
+
+    weight
+}
+
+/// Indexes existing stakes in `Maturing`, since they're no longer found by iterating over
+/// `StakedAmounts` at each distribution.
+///
+/// Existing stakes have no `RewardCheckpoints`, which is correct since `RewardPerCoinShare` starts
+/// at zero. Their rewards so far were already added to their balances.
+pub mod v2 {
+    use super::*;
+
+    pub fn migrate<T: Config>() -> Weight {
+        // Rebuilt from scratch so that chains which started on V2 aren't indexed twice.
+        Maturing::<T>::remove_all();
+
+        let stakes = StakedAmounts::<T>::iter()
+            .map(|(who, unstake_at, _)| (who, unstake_at))
+            .collect::<Vec<_>>();
+        let count = stakes.len() as Weight;
+        for (who, unstake_at) in stakes {
+            Pallet::<T>::schedule_release(&who, unstake_at);
+        }
+
+        T::DbWeight::get().reads_writes(count + 1, count + 1)
+    }
+}
+
+/// Converts `StakedAmounts` from a map of shares per coin to balance into a bounded list of tiers,
+/// and `TotalCoinShares`, `RewardPerCoinShare` and `RewardCheckpoints` from arbitrary
+/// precision integers into `u128` and `FixedU128`.
+///
+/// Values too large for the new types saturate, which no stake on a live chain comes close to.
+/// Stakes in more than `MaxTiersPerStake` tiers have the excess merged into their tier with the
+/// fewest shares per coin, which only lowers their rewards.
+pub mod v3 {
+    use super::*;
+    use crate::pallet::{
+        BalanceOf, MaxTiersPerStake, RewardCheckpoints, RewardPerCoinShare, ShareBalance,
+        TierBalance, TotalCoinShares,
+    };
+    use codec::{alloc::collections::BTreeMap, Decode};
+    use core::convert::TryFrom;
+    use num_bigint::BigUint;
+    use sp_arithmetic::{FixedPointNumber, FixedU128};
+
+    /// V2 scaled `RewardPerCoinShare` by `2^128`.
+    const OLD_REWARD_PRECISION_BITS: usize = 128;
+
+    #[derive(Decode)]
+    struct OldEncodableBigUint {
+        digits: Vec<u32>,
+    }
+
+    impl OldEncodableBigUint {
+        fn into_biguint(self) -> BigUint {
+            BigUint::new(self.digits)
+        }
+    }
+
+    #[derive(Decode)]
+    struct OldShareBalance<B> {
+        map: BTreeMap<u32, B>,
+    }
+
+    fn saturating_u128(n: BigUint) -> u128 {
+        u128::try_from(&n).unwrap_or(u128::MAX)
+    }
+
+    fn reward_per_coin_share(old: OldEncodableBigUint) -> FixedU128 {
+        let scaled = (old.into_biguint() * FixedU128::DIV) >> OLD_REWARD_PRECISION_BITS;
+        FixedU128::from_inner(saturating_u128(scaled))
+    }
+
+    pub fn migrate<T: Config>() -> Weight {
+        let mut count: Weight = 0;
+
+        // Recomputed rather than converted, so that it stays the sum of every stake's coin
+        // shares even if some of them saturated.
+        let mut total_coin_shares = 0u128;
+        StakedAmounts::<T>::translate::<OldShareBalance<BalanceOf<T>>, _>(|_, _, old| {
+            count += 1;
+
+            // In ascending order of shares per coin, since the map is ordered by them.
+            let mut tiers = old
+                .map
+                .into_iter()
+                .map(|(shares, balance)| TierBalance { shares, balance })
+                .collect::<Vec<_>>();
+            while tiers.len() > MaxTiersPerStake::get() as usize {
+                let merged = tiers.remove(1);
+                tiers[0].balance += merged.balance;
+            }
+
+            let sb = ShareBalance::from_tiers(tiers).unwrap_or_default();
+            total_coin_shares = total_coin_shares.saturating_add(sb.coin_shares());
+
+            Some(sb)
+        });
+        TotalCoinShares::<T>::put(total_coin_shares);
+
+        let _ = RewardPerCoinShare::<T>::translate::<OldEncodableBigUint, _>(|old| {
+            old.map(reward_per_coin_share)
+        });
+        RewardCheckpoints::<T>::translate::<OldEncodableBigUint, _>(|_, _, old| {
+            count += 1;
+            Some(reward_per_coin_share(old))
+        });
+
+        T::DbWeight::get().reads_writes(count + 2, count + 2)
+    }
+}
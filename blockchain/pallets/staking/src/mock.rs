@@ -1,4 +1,6 @@
 use crate as fractal_staking;
+use codec::alloc::collections::BTreeSet;
+use core::cell::RefCell;
 use frame_support::parameter_types;
 use frame_system as system;
 use sp_core::H256;
@@ -20,7 +22,7 @@ frame_support::construct_runtime!(
     {
         System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
         Balances: pallet_balances::{Pallet, Call, Storage, Event<T>},
-        FractalStaking: fractal_staking::{Pallet, Call, Storage, Event<T>},
+        FractalStaking: fractal_staking::{Pallet, Call, Storage, Config<T>, Event<T>},
     }
 );
 
@@ -74,6 +76,8 @@ parameter_types! {
 
     pub const DistributionSource: u64 = 42;
     pub const HoldingAccount: u64 = 43;
+    pub const MaxMaturingPerBlock: u32 = 5;
+    pub const MaxStakesPerAccount: u32 = 8;
 }
 
 impl fractal_staking::Config for Test {
@@ -84,8 +88,64 @@ impl fractal_staking::Config for Test {
     type DistributeEveryNBlocks = DistributeEveryNBlocks;
 
     type DistributionSource = DistributionSource;
+
+    type EarlyUnstakePenaltyHandler = crate::Redistribute<Test>;
+
+    type Verification = Verification;
+
+    type MaxMaturingPerBlock = MaxMaturingPerBlock;
+
+    type StakeLocks = ();
+
+    type MaxStakesPerAccount = MaxStakesPerAccount;
+}
+
+thread_local! {
+    static VERIFIED: RefCell<BTreeSet<u64>> = RefCell::new(BTreeSet::new());
+}
+
+/// Verifies the accounts given to `set_verified`, and tells `FractalStaking` when they change.
+pub struct Verification;
+
+impl Verification {
+    pub fn set_verified(who: u64, verified: bool) {
+        Self::set_verified_silently(who, verified);
+
+        <FractalStaking as crate::OnVerificationChanged<u64>>::on_verification_changed(&who);
+    }
+
+    /// Changes `who`'s verification without telling `FractalStaking`.
+    pub fn set_verified_silently(who: u64, verified: bool) {
+        VERIFIED.with(|v| {
+            if verified {
+                v.borrow_mut().insert(who);
+            } else {
+                v.borrow_mut().remove(&who);
+            }
+        });
+    }
+}
+
+impl crate::IsVerified<u64> for Verification {
+    fn is_verified(who: &u64) -> bool {
+        VERIFIED.with(|v| v.borrow().contains(who))
+    }
 }
 
 pub fn new_test_ext() -> sp_io::TestExternalities {
-    GenesisConfig::default().build_storage().unwrap().into()
+    genesis_test_ext(Default::default())
+}
+
+pub fn genesis_test_ext(
+    fractal_staking: fractal_staking::GenesisConfig<Test>,
+) -> sp_io::TestExternalities {
+    VERIFIED.with(|v| v.borrow_mut().clear());
+
+    GenesisConfig {
+        fractal_staking,
+        ..Default::default()
+    }
+    .build_storage()
+    .unwrap()
+    .into()
 }
@@ -1,9 +1,9 @@
 use crate::mock::*;
+use core::convert::TryInto;
 use frame_support::{
     assert_noop, assert_ok,
-    traits::{Currency, OnFinalize, OnInitialize},
+    traits::{Currency, Get, OnFinalize, OnInitialize},
 };
-use num_bigint::BigUint;
 
 #[cfg(test)]
 mod register_identity {
@@ -37,18 +37,42 @@ mod register_identity {
     }
 
     fn check_invariants() {
-        let coin_shares: BigUint = crate::pallet::StakedAmounts::<Test>::iter()
+        let coin_shares: u128 = crate::pallet::StakedAmounts::<Test>::iter()
             .map(|(_, _, sb)| sb.coin_shares())
             .sum();
         assert_eq!(
             coin_shares,
-            crate::pallet::TotalCoinShares::<Test>::get().into(),
+            crate::pallet::TotalCoinShares::<Test>::get(),
             "TotalCoinShares incorrect"
         );
+        let verified_coin_shares: u128 = crate::pallet::StakedAmounts::<Test>::iter()
+            .filter(|(account, _, _)| crate::pallet::VerifiedStakers::<Test>::contains_key(account))
+            .map(|(_, _, sb)| sb.coin_shares())
+            .sum();
+        assert_eq!(
+            verified_coin_shares,
+            crate::pallet::VerifiedCoinShares::<Test>::get(),
+            "VerifiedCoinShares incorrect"
+        );
 
         for (_, _, sb) in crate::pallet::StakedAmounts::<Test>::iter() {
             assert_ne!(sb.balance(), 0);
         }
+
+        let pending: u64 = crate::pallet::StakedAmounts::<Test>::iter()
+            .map(|(account, unstake_at, _)| FractalStaking::pending_rewards(&account, unstake_at))
+            .sum();
+        assert!(
+            Balances::reserved_balance(&<Test as crate::Config>::DistributionSource::get())
+                >= pending,
+            "Pending rewards not reserved"
+        );
+    }
+
+    fn pending_rewards(account: u64) -> u64 {
+        crate::StakedAmounts::<Test>::iter_prefix(account)
+            .map(|(unstake_at, _)| FractalStaking::pending_rewards(&account, unstake_at))
+            .sum()
     }
 
     fn staked_balance(account: u64) -> u64 {
@@ -57,6 +81,19 @@ mod register_identity {
             .sum()
     }
 
+    /// Claims the rewards of each of `account`'s stakes.
+    fn claim_rewards(account: u64) {
+        let stakes = crate::StakedAmounts::<Test>::iter_prefix(account)
+            .map(|(unstake_at, _)| unstake_at)
+            .collect::<Vec<_>>();
+        for unstake_at in stakes {
+            assert_ok!(FractalStaking::claim_rewards(
+                Origin::signed(account),
+                unstake_at
+            ));
+        }
+    }
+
     fn run_to_distribution() {
         let distribute_every_n = <Test as crate::Config>::DistributeEveryNBlocks::get();
 
@@ -73,6 +110,13 @@ mod register_identity {
         Balances::make_free_balance_be(&<Test as crate::Config>::DistributionSource::get(), amount);
     }
 
+    fn staking_events() -> Vec<crate::Event<Test>> {
+        System::events()
+            .into_iter()
+            .filter_map(|record| record.event.try_into().ok())
+            .collect()
+    }
+
     fn step_past_lock_period() {
         for _ in 0..DEFAULT_LOCK {
             step_block();
@@ -92,6 +136,7 @@ mod register_identity {
             ));
 
             run_to_distribution();
+            claim_rewards(1);
 
             assert_eq!(staked_balance(1), 200_000);
         });
@@ -110,8 +155,10 @@ mod register_identity {
             ));
 
             run_to_distribution();
+            claim_rewards(1);
 
             assert_eq!(Balances::free_balance(1), 0);
+            assert_eq!(Balances::reserved_balance(1), 200_000);
         });
     }
 
@@ -146,6 +193,8 @@ mod register_identity {
             ));
 
             run_to_distribution();
+            claim_rewards(1);
+            claim_rewards(2);
 
             assert_eq!(staked_balance(1), 150_000);
             assert_eq!(staked_balance(2), 150_000);
@@ -197,6 +246,8 @@ mod register_identity {
             set_distribution_source(20_000);
             run_to_distribution();
 
+            claim_rewards(1);
+
             assert_eq!(Balances::free_balance(1), 50_000 + 10_000);
             assert_eq!(staked_balance(1), 50_000 + 10_000);
         });
@@ -225,6 +276,7 @@ mod register_identity {
             ));
 
             run_to_distribution();
+            claim_rewards(2);
 
             assert_eq!(staked_balance(2), 200_000);
         });
@@ -250,12 +302,16 @@ mod register_identity {
             ));
 
             run_to_distribution();
+            claim_rewards(1);
+            claim_rewards(2);
 
             assert_eq!(staked_balance(1), 100_000 + 66_666);
             assert_eq!(staked_balance(2), 50_000 + 33_333);
         });
     }
 
+    /// Distribution reserves all of `DistributionSource`'s free balance, and stakes are paid out
+    /// of that reserve. What rounding leaves over is only seen once every stake has been paid.
     #[test]
     fn returns_excess_to_distribution_account() {
         run_test(|| {
@@ -275,10 +331,11 @@ mod register_identity {
                 50_000
             ));
 
+            step_past_lock_period();
             run_to_distribution();
 
             assert_eq!(
-                Balances::free_balance(&<Test as crate::Config>::DistributionSource::get()),
+                Balances::total_balance(&<Test as crate::Config>::DistributionSource::get()),
                 1
             );
         });
@@ -306,6 +363,8 @@ mod register_identity {
             assert_ok!(FractalStaking::stake(Origin::signed(2), 200, 100_000));
 
             run_to_distribution();
+            claim_rewards(1);
+            claim_rewards(2);
 
             assert_eq!(Balances::reserved_balance(1), 133_333);
             assert_eq!(Balances::reserved_balance(2), 166_666);
@@ -402,10 +461,110 @@ mod register_identity {
 
                 set_distribution_source(100_000);
                 run_to_distribution();
+                claim_rewards(1);
 
                 assert_eq!(staked_balance(1), 200_000);
             });
         }
+
+        #[test]
+        fn limits_tiers_per_stake() {
+            run_test(|| {
+                let tiers = crate::MaxTiersPerStake::get() as u64;
+                for extra in 1..=tiers {
+                    assert_ok!(FractalStaking::set_lock_period_shares(
+                        Origin::root(),
+                        DEFAULT_LOCK + extra,
+                        10 + extra as u32
+                    ));
+                }
+
+                let _ = Balances::deposit_creating(&1, 100_000);
+                for extra in (1..=tiers).rev() {
+                    assert_ok!(FractalStaking::stake(
+                        Origin::signed(1),
+                        DEFAULT_LOCK + extra,
+                        10_000
+                    ));
+                    step_block();
+                }
+
+                assert_noop!(
+                    FractalStaking::stake(Origin::signed(1), DEFAULT_LOCK, 10_000),
+                    Error::TooManyTiers
+                );
+                // Tiers the stake already holds balance in can still grow.
+                assert_ok!(FractalStaking::set_lock_period_shares(
+                    Origin::root(),
+                    DEFAULT_LOCK,
+                    11
+                ));
+                assert_ok!(FractalStaking::stake(
+                    Origin::signed(1),
+                    DEFAULT_LOCK,
+                    10_000
+                ));
+            });
+        }
+
+        #[test]
+        fn releases_stakes_past_maturing_limit_at_next_distribution() {
+            run_test(|| {
+                let max = <Test as crate::Config>::MaxMaturingPerBlock::get() as u64;
+                for account in 1..=max + 1 {
+                    let _ = Balances::deposit_creating(&account, 100_000);
+                    assert_ok!(FractalStaking::stake(
+                        Origin::signed(account),
+                        DEFAULT_LOCK,
+                        50_000
+                    ));
+                }
+
+                step_past_lock_period();
+                run_to_distribution();
+
+                for account in 1..=max {
+                    assert_eq!(Balances::reserved_balance(&account), 0);
+                }
+                assert_eq!(Balances::reserved_balance(&(max + 1)), 50_000);
+
+                run_to_distribution();
+
+                assert_eq!(Balances::reserved_balance(&(max + 1)), 0);
+            });
+        }
+    }
+
+    #[test]
+    fn limits_stakes_per_account() {
+        run_test(|| {
+            assert_ok!(FractalStaking::set_lock_period_shares(
+                Origin::root(),
+                DEFAULT_LOCK - 1,
+                10
+            ));
+            let max = <Test as crate::Config>::MaxStakesPerAccount::get();
+            let _ = Balances::deposit_creating(&1, 100_000);
+            for _ in 0..max {
+                assert_ok!(FractalStaking::stake(
+                    Origin::signed(1),
+                    DEFAULT_LOCK,
+                    1_000
+                ));
+                step_block();
+            }
+
+            assert_noop!(
+                FractalStaking::stake(Origin::signed(1), DEFAULT_LOCK, 1_000),
+                Error::TooManyStakes
+            );
+            // The last stake can still be added to.
+            assert_ok!(FractalStaking::stake(
+                Origin::signed(1),
+                DEFAULT_LOCK - 1,
+                1_000
+            ));
+        });
     }
 
     #[test]
@@ -422,8 +581,1409 @@ mod register_identity {
             ));
 
             run_to_distribution();
+            claim_rewards(1);
 
             assert_eq!(staked_balance(1), u64::MAX);
         });
     }
+
+    #[cfg(test)]
+    mod reward_accumulator {
+        use super::*;
+        use core::convert::TryFrom;
+        use frame_support::{storage::unhashed, traits::ReservableCurrency};
+        use maplit::btreemap;
+
+        fn stake(account: u64, lock_period: u64, amount: u64) {
+            let _ = Balances::deposit_creating(&account, amount);
+            assert_ok!(FractalStaking::stake(
+                Origin::signed(account),
+                lock_period,
+                amount
+            ));
+        }
+
+        #[test]
+        fn distribution_does_not_touch_stakes() {
+            run_test(|| {
+                set_distribution_source(100_000);
+                stake(1, DEFAULT_LOCK, 100_000);
+
+                run_to_distribution();
+
+                assert_eq!(Balances::reserved_balance(&1), 100_000);
+                assert_eq!(Balances::free_balance(&1), 0);
+                assert_eq!(pending_rewards(1), 100_000);
+            });
+        }
+
+        #[test]
+        fn compounds_rewards_when_stake_is_increased() {
+            run_test(|| {
+                assert_ok!(FractalStaking::set_lock_period_shares(
+                    Origin::root(),
+                    DEFAULT_LOCK - 10,
+                    10
+                ));
+                set_distribution_source(100_000);
+                stake(1, DEFAULT_LOCK, 100_000);
+                run_to_distribution();
+
+                // Unlocks in the same block as the first stake, so it's added to it.
+                stake(1, DEFAULT_LOCK - 10, 50_000);
+
+                assert_eq!(crate::StakedAmounts::<Test>::iter_prefix(1).count(), 1);
+                assert_eq!(Balances::free_balance(&1), 0);
+                assert_eq!(pending_rewards(1), 0);
+                assert_eq!(staked_balance(1), 250_000);
+                assert_eq!(Balances::reserved_balance(&1), 250_000);
+            });
+        }
+
+        #[test]
+        fn claimed_rewards_earn_rewards() {
+            run_test(|| {
+                set_distribution_source(100_000);
+                stake(1, DEFAULT_LOCK, 100_000);
+                stake(2, DEFAULT_LOCK, 100_000);
+                run_to_distribution();
+                claim_rewards(1);
+
+                set_distribution_source(250_000);
+                run_to_distribution();
+                claim_rewards(1);
+                claim_rewards(2);
+
+                assert_eq!(Balances::reserved_balance(&1), 150_000 + 150_000);
+                assert_eq!(Balances::reserved_balance(&2), 150_000 + 100_000);
+            });
+        }
+
+        #[test]
+        fn claiming_requires_a_stake() {
+            run_test(|| {
+                let unstake_at = System::block_number() + DEFAULT_LOCK;
+                stake(1, DEFAULT_LOCK, 100_000);
+
+                assert_noop!(
+                    FractalStaking::claim_rewards(Origin::signed(2), unstake_at),
+                    Error::NoStakeUnlockingAt
+                );
+                assert_noop!(
+                    FractalStaking::claim_rewards(Origin::signed(1), unstake_at + 1),
+                    Error::NoStakeUnlockingAt
+                );
+            });
+        }
+
+        #[test]
+        fn reserves_weight_for_maturing_stakes() {
+            run_test(|| {
+                let every_n = <Test as crate::Config>::DistributeEveryNBlocks::get();
+                stake(1, DEFAULT_LOCK, 100_000);
+                stake(2, DEFAULT_LOCK, 100_000);
+                let release_block =
+                    FractalStaking::release_block(System::block_number() + DEFAULT_LOCK);
+
+                let idle = FractalStaking::on_initialize(release_block - every_n);
+                let releasing = FractalStaking::on_initialize(release_block);
+
+                assert_eq!(FractalStaking::on_initialize(release_block - 1), 0);
+                assert!(releasing > idle);
+            });
+        }
+
+        #[test]
+        fn later_stakes_do_not_share_earlier_rewards() {
+            run_test(|| {
+                set_distribution_source(100_000);
+                stake(1, DEFAULT_LOCK, 100_000);
+                run_to_distribution();
+
+                stake(2, DEFAULT_LOCK, 100_000);
+                set_distribution_source(50_000);
+                run_to_distribution();
+                claim_rewards(1);
+                claim_rewards(2);
+
+                assert_eq!(staked_balance(1), 225_000);
+                assert_eq!(staked_balance(2), 125_000);
+            });
+        }
+
+        #[test]
+        fn keeps_rewards_without_stakers() {
+            run_test(|| {
+                set_distribution_source(100_000);
+
+                run_to_distribution();
+
+                assert_eq!(
+                    Balances::free_balance(&<Test as crate::Config>::DistributionSource::get()),
+                    100_000
+                );
+            });
+        }
+
+        /// Stores a stake as it was encoded before V3, a map of shares per coin to balance.
+        fn put_v2_stake(account: u64, unstake_at: u64, amount: u64) {
+            let _ = Balances::deposit_creating(&account, amount);
+            assert_ok!(Balances::reserve(&account, amount));
+
+            unhashed::put(
+                &crate::StakedAmounts::<Test>::hashed_key_for(account, unstake_at),
+                &btreemap! { 10u32 => amount },
+            );
+            let coin_shares = u32::try_from(amount * 10).unwrap();
+            unhashed::put(&crate::TotalCoinShares::<Test>::hashed_key(), &vec![coin_shares]);
+        }
+
+        #[test]
+        fn migrates_existing_stakes_to_maturing() {
+            run_test(|| {
+                put_v2_stake(1, System::block_number() + DEFAULT_LOCK, 100_000);
+                crate::pallet::StorageVersion::<Test>::put(crate::Releases::V1);
+
+                crate::migrations::migrate::<Test>();
+                step_past_lock_period();
+                run_to_distribution();
+
+                assert_eq!(Balances::free_balance(&1), 100_000);
+                assert_eq!(staked_balance(1), 0);
+                assert_eq!(
+                    crate::pallet::StorageVersion::<Test>::get(),
+                    crate::Releases::V3
+                );
+            });
+        }
+
+        #[test]
+        fn migrates_to_fixed_width() {
+            run_test(|| {
+                let source = <Test as crate::Config>::DistributionSource::get();
+                set_distribution_source(62_500);
+                assert_ok!(Balances::reserve(&source, 62_500));
+
+                let unstake_at = System::block_number() + DEFAULT_LOCK;
+                put_v2_stake(1, unstake_at, 100_000);
+                // 1/16 per coin share, scaled by 2^128.
+                unhashed::put(
+                    &crate::RewardPerCoinShare::<Test>::hashed_key(),
+                    &vec![0u32, 0, 0, 1 << 28],
+                );
+                crate::pallet::StorageVersion::<Test>::put(crate::Releases::V2);
+
+                crate::migrations::migrate::<Test>();
+
+                let sb = crate::StakedAmounts::<Test>::get(1, unstake_at);
+                assert_eq!(
+                    sb.tiers(),
+                    &[crate::TierBalance {
+                        shares: 10,
+                        balance: 100_000
+                    }]
+                );
+                assert_eq!(sb.coin_shares(), 1_000_000);
+                assert_eq!(crate::TotalCoinShares::<Test>::get(), 1_000_000);
+                assert_eq!(pending_rewards(1), 62_500);
+                assert_eq!(
+                    crate::pallet::StorageVersion::<Test>::get(),
+                    crate::Releases::V3
+                );
+            });
+        }
+
+        #[test]
+        fn merges_excess_tiers_into_the_lowest() {
+            run_test(|| {
+                let unstake_at = System::block_number() + DEFAULT_LOCK;
+                put_v2_stake(1, unstake_at, 50_000);
+                unhashed::put(
+                    &crate::StakedAmounts::<Test>::hashed_key_for(1, unstake_at),
+                    &btreemap! {
+                        10u32 => 10_000u64,
+                        11 => 10_000,
+                        12 => 10_000,
+                        13 => 10_000,
+                        14 => 10_000,
+                    },
+                );
+                crate::pallet::StorageVersion::<Test>::put(crate::Releases::V2);
+
+                crate::migrations::migrate::<Test>();
+
+                let sb = crate::StakedAmounts::<Test>::get(1, unstake_at);
+                assert_eq!(
+                    sb.tiers()
+                        .iter()
+                        .map(|tier| (tier.shares, tier.balance))
+                        .collect::<Vec<_>>(),
+                    vec![(10, 20_000), (12, 10_000), (13, 10_000), (14, 10_000)]
+                );
+                assert_eq!(crate::TotalCoinShares::<Test>::get(), 590_000);
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod roll_over {
+        use super::*;
+        use crate::RollOver;
+
+        fn stake(account: u64, amount: u64) -> u64 {
+            let _ = Balances::deposit_creating(&account, amount);
+            assert_ok!(FractalStaking::stake(
+                Origin::signed(account),
+                DEFAULT_LOCK,
+                amount
+            ));
+
+            System::block_number() + DEFAULT_LOCK
+        }
+
+        fn set_roll_over(account: u64, unstake_at: u64, lock_period: u64, compound: bool) {
+            assert_ok!(FractalStaking::set_roll_over(
+                Origin::signed(account),
+                account,
+                unstake_at,
+                Some(RollOver {
+                    lock_period,
+                    compound,
+                })
+            ));
+        }
+
+        fn unlocks(account: u64) -> Vec<u64> {
+            crate::StakedAmounts::<Test>::iter_prefix(account)
+                .map(|(unstake_at, _)| unstake_at)
+                .collect()
+        }
+
+        fn run_past(block: u64) {
+            while System::block_number() <= block {
+                step_block();
+            }
+            run_to_distribution();
+        }
+
+        #[test]
+        fn restakes_matured_stake() {
+            run_test(|| {
+                set_distribution_source(100_000);
+                let unstake_at = stake(1, 100_000);
+                set_roll_over(1, unstake_at, DEFAULT_LOCK, false);
+
+                run_past(unstake_at);
+
+                let release_block = FractalStaking::release_block(unstake_at);
+                assert_eq!(unlocks(1), vec![release_block + DEFAULT_LOCK]);
+                assert_eq!(Balances::reserved_balance(&1), 100_000);
+                assert_eq!(Balances::free_balance(&1), 100_000);
+            });
+        }
+
+        #[test]
+        fn compounds_rewards_into_new_stake() {
+            run_test(|| {
+                set_distribution_source(100_000);
+                let unstake_at = stake(1, 100_000);
+                set_roll_over(1, unstake_at, DEFAULT_LOCK, true);
+
+                run_past(unstake_at);
+
+                assert_eq!(Balances::reserved_balance(&1), 200_000);
+                assert_eq!(Balances::free_balance(&1), 0);
+                assert_eq!(staked_balance(1), 200_000);
+            });
+        }
+
+        #[test]
+        fn compounded_rewards_earn_rewards() {
+            run_test(|| {
+                set_distribution_source(100_000);
+                let unstake_at = stake(1, 100_000);
+                stake(2, 100_000);
+                set_roll_over(1, unstake_at, DEFAULT_LOCK, true);
+                run_past(unstake_at);
+
+                stake(3, 100_000);
+                set_distribution_source(100_000);
+                run_to_distribution();
+                claim_rewards(1);
+                claim_rewards(3);
+
+                assert_eq!(staked_balance(1), 150_000 + 60_000);
+                assert_eq!(staked_balance(3), 100_000 + 40_000);
+            });
+        }
+
+        #[test]
+        fn restakes_into_other_tier() {
+            run_test(|| {
+                assert_ok!(FractalStaking::set_lock_period_shares(
+                    Origin::root(),
+                    200,
+                    20
+                ));
+                let unstake_at = stake(1, 100_000);
+                set_roll_over(1, unstake_at, 200, false);
+
+                run_past(unstake_at);
+
+                let release_block = FractalStaking::release_block(unstake_at);
+                assert_eq!(unlocks(1), vec![release_block + 200]);
+                assert_eq!(
+                    crate::StakedAmounts::<Test>::get(1, release_block + 200).coin_shares(),
+                    100_000 * 20
+                );
+            });
+        }
+
+        #[test]
+        fn keeps_rolling_over() {
+            run_test(|| {
+                let unstake_at = stake(1, 100_000);
+                set_roll_over(1, unstake_at, DEFAULT_LOCK, false);
+
+                run_past(unstake_at + 2 * DEFAULT_LOCK);
+
+                assert_eq!(staked_balance(1), 100_000);
+                assert_eq!(Balances::free_balance(&1), 0);
+            });
+        }
+
+        #[test]
+        fn can_be_cancelled() {
+            run_test(|| {
+                let unstake_at = stake(1, 100_000);
+                set_roll_over(1, unstake_at, DEFAULT_LOCK, false);
+                assert_ok!(FractalStaking::set_roll_over(Origin::signed(1), 1, unstake_at, None));
+
+                run_past(unstake_at);
+
+                assert_eq!(staked_balance(1), 0);
+                assert_eq!(Balances::free_balance(&1), 100_000);
+            });
+        }
+
+        #[test]
+        fn requires_stake_and_known_tier() {
+            run_test(|| {
+                let unstake_at = stake(1, 100_000);
+                let roll_over = |lock_period| {
+                    Some(RollOver {
+                        lock_period,
+                        compound: false,
+                    })
+                };
+
+                assert_noop!(
+                    FractalStaking::set_roll_over(
+                        Origin::signed(2),
+                        2,
+                        unstake_at,
+                        roll_over(DEFAULT_LOCK)
+                    ),
+                    Error::NoStakeUnlockingAt
+                );
+                assert_noop!(
+                    FractalStaking::set_roll_over(Origin::signed(1), 1, unstake_at, roll_over(7)),
+                    Error::UnknownLockPeriod
+                );
+            });
+        }
+
+        #[test]
+        fn unstaking_early_clears_roll_over() {
+            run_test(|| {
+                assert_ok!(FractalStaking::set_early_unstake_penalty(
+                    Origin::root(),
+                    Some(sp_runtime::Perbill::zero())
+                ));
+                let unstake_at = stake(1, 100_000);
+                set_roll_over(1, unstake_at, DEFAULT_LOCK, false);
+
+                assert_ok!(FractalStaking::unstake_early(Origin::signed(1), 1, unstake_at));
+
+                assert_eq!(crate::RollOvers::<Test>::get(1, unstake_at), None);
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod staking_summary {
+        use super::*;
+        use crate::{RollOver, StakePosition};
+        use sp_runtime::Perbill;
+
+        fn stake(account: u64, lock_period: u64, amount: u64) -> u64 {
+            let _ = Balances::deposit_creating(&account, amount);
+            assert_ok!(FractalStaking::stake(
+                Origin::signed(account),
+                lock_period,
+                amount
+            ));
+
+            System::block_number() + lock_period
+        }
+
+        #[test]
+        fn empty_without_stakes() {
+            run_test(|| {
+                let summary = FractalStaking::staking_summary(&1);
+
+                assert_eq!(summary.positions, vec![]);
+                assert_eq!(summary.pending_rewards, 0);
+                assert_eq!(summary.coin_shares_fraction, Perbill::zero());
+                assert_eq!(summary.estimated_next_reward, 0);
+            });
+        }
+
+        #[test]
+        fn lists_each_tier_of_each_stake() {
+            run_test(|| {
+                assert_ok!(FractalStaking::set_lock_period_shares(
+                    Origin::root(),
+                    DEFAULT_LOCK + 1,
+                    20
+                ));
+                let first_unstake_at = stake(1, DEFAULT_LOCK + 1, 50_000);
+                step_block();
+                stake(1, DEFAULT_LOCK, 30_000);
+                step_block();
+                let second_unstake_at = stake(1, DEFAULT_LOCK, 20_000);
+                let roll_over = RollOver {
+                    lock_period: DEFAULT_LOCK,
+                    compound: true,
+                };
+                assert_ok!(FractalStaking::set_roll_over(
+                    Origin::signed(1),
+                    1,
+                    second_unstake_at,
+                    Some(roll_over.clone())
+                ));
+
+                let mut positions = FractalStaking::staking_summary(&1).positions;
+                positions.sort_by_key(|p| (p.unstake_at, p.shares));
+
+                assert_eq!(
+                    positions,
+                    vec![
+                        StakePosition {
+                            unstake_at: first_unstake_at,
+                            shares: 10,
+                            balance: 30_000,
+                            roll_over: None,
+                        },
+                        StakePosition {
+                            unstake_at: first_unstake_at,
+                            shares: 20,
+                            balance: 50_000,
+                            roll_over: None,
+                        },
+                        StakePosition {
+                            unstake_at: second_unstake_at,
+                            shares: 10,
+                            balance: 20_000,
+                            roll_over: Some(roll_over),
+                        },
+                    ]
+                );
+            });
+        }
+
+        #[test]
+        fn estimates_share_of_next_distribution() {
+            run_test(|| {
+                stake(1, DEFAULT_LOCK, 100_000);
+                stake(2, DEFAULT_LOCK, 300_000);
+                set_distribution_source(100_000);
+
+                let summary = FractalStaking::staking_summary(&1);
+                assert_eq!(summary.coin_shares_fraction, Perbill::from_percent(25));
+                assert_eq!(summary.estimated_next_reward, 25_000);
+
+                run_to_distribution();
+
+                let summary = FractalStaking::staking_summary(&1);
+                assert_eq!(summary.pending_rewards, 25_000);
+                assert_eq!(summary.estimated_next_reward, 0);
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod lock_period_tiers {
+        use super::*;
+
+        fn stake(account: u64, lock_period: u64, amount: u64) -> u64 {
+            let _ = Balances::deposit_creating(&account, amount);
+            assert_ok!(FractalStaking::stake(
+                Origin::signed(account),
+                lock_period,
+                amount
+            ));
+
+            System::block_number() + lock_period
+        }
+
+        #[test]
+        fn lists_tiers_by_lock_period() {
+            run_test(|| {
+                assert_ok!(FractalStaking::set_lock_period_shares(Origin::root(), 300, 30));
+                assert_ok!(FractalStaking::set_lock_period_shares(Origin::root(), 50, 5));
+
+                assert_eq!(
+                    FractalStaking::lock_period_tiers(),
+                    vec![(50, 5), (DEFAULT_LOCK, 10), (300, 30)]
+                );
+            });
+        }
+
+        #[test]
+        fn retired_tier_rejects_new_stakes() {
+            run_test(|| {
+                assert_ok!(FractalStaking::retire_lock_period(Origin::root(), DEFAULT_LOCK));
+
+                let _ = Balances::deposit_creating(&1, 100_000);
+                assert_noop!(
+                    FractalStaking::stake(Origin::signed(1), DEFAULT_LOCK, 100_000),
+                    Error::UnknownLockPeriod
+                );
+                assert_eq!(FractalStaking::lock_period_tiers(), vec![]);
+            });
+        }
+
+        #[test]
+        fn retired_tier_stakes_keep_running() {
+            run_test(|| {
+                set_distribution_source(100_000);
+                stake(1, DEFAULT_LOCK, 100_000);
+                assert_ok!(FractalStaking::retire_lock_period(Origin::root(), DEFAULT_LOCK));
+
+                run_to_distribution();
+                claim_rewards(1);
+                assert_eq!(staked_balance(1), 200_000);
+
+                step_past_lock_period();
+                run_to_distribution();
+                assert_eq!(Balances::free_balance(&1), 200_000);
+            });
+        }
+
+        #[test]
+        fn does_not_roll_over_into_retired_tier() {
+            run_test(|| {
+                let unstake_at = stake(1, DEFAULT_LOCK, 100_000);
+                assert_ok!(FractalStaking::set_roll_over(
+                    Origin::signed(1),
+                    1,
+                    unstake_at,
+                    Some(crate::RollOver {
+                        lock_period: DEFAULT_LOCK,
+                        compound: false,
+                    })
+                ));
+                assert_ok!(FractalStaking::retire_lock_period(Origin::root(), DEFAULT_LOCK));
+
+                step_past_lock_period();
+                run_to_distribution();
+
+                assert_eq!(staked_balance(1), 0);
+                assert_eq!(Balances::free_balance(&1), 100_000);
+            });
+        }
+
+        #[test]
+        fn retiring_requires_known_tier() {
+            run_test(|| {
+                assert_noop!(
+                    FractalStaking::retire_lock_period(Origin::root(), 7),
+                    Error::UnknownLockPeriod
+                );
+            });
+        }
+
+        #[test]
+        fn changed_shares_only_apply_to_new_stakes() {
+            run_test(|| {
+                set_distribution_source(90_000);
+                stake(1, DEFAULT_LOCK, 100_000);
+                assert_ok!(FractalStaking::set_lock_period_shares(
+                    Origin::root(),
+                    DEFAULT_LOCK,
+                    20
+                ));
+                stake(2, DEFAULT_LOCK, 100_000);
+
+                run_to_distribution();
+                claim_rewards(1);
+                claim_rewards(2);
+
+                assert_eq!(staked_balance(1), 100_000 + 30_000);
+                assert_eq!(staked_balance(2), 100_000 + 60_000);
+            });
+        }
+
+        #[test]
+        fn emits_tier_events() {
+            run_test(|| {
+                assert_ok!(FractalStaking::set_lock_period_shares(
+                    Origin::root(),
+                    DEFAULT_LOCK,
+                    20
+                ));
+                assert_ok!(FractalStaking::retire_lock_period(Origin::root(), DEFAULT_LOCK));
+
+                assert_eq!(
+                    staking_events(),
+                    vec![
+                        crate::Event::LockPeriodSharesSet {
+                            lock_period: DEFAULT_LOCK,
+                            shares: 10,
+                            previous: None,
+                        },
+                        crate::Event::LockPeriodSharesSet {
+                            lock_period: DEFAULT_LOCK,
+                            shares: 20,
+                            previous: Some(10),
+                        },
+                        crate::Event::LockPeriodRetired {
+                            lock_period: DEFAULT_LOCK,
+                            shares: 20,
+                        },
+                    ]
+                );
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod unstake_early {
+        use super::*;
+        use sp_runtime::Perbill;
+
+        fn stake(account: u64, amount: u64) -> u64 {
+            let _ = Balances::deposit_creating(&account, amount);
+            assert_ok!(FractalStaking::stake(
+                Origin::signed(account),
+                DEFAULT_LOCK,
+                amount
+            ));
+
+            System::block_number() + DEFAULT_LOCK
+        }
+
+        fn set_penalty(percent: u32) {
+            assert_ok!(FractalStaking::set_early_unstake_penalty(
+                Origin::root(),
+                Some(Perbill::from_percent(percent))
+            ));
+        }
+
+        fn distribution_source_balance() -> u64 {
+            Balances::free_balance(&<Test as crate::Config>::DistributionSource::get())
+        }
+
+        #[test]
+        fn requires_penalty_to_be_set() {
+            run_test(|| {
+                let unstake_at = stake(1, 100_000);
+
+                assert_noop!(
+                    FractalStaking::unstake_early(Origin::signed(1), 1, unstake_at),
+                    Error::EarlyUnstakeNotAllowed
+                );
+            });
+        }
+
+        #[test]
+        fn returns_stake_less_penalty() {
+            run_test(|| {
+                set_penalty(10);
+                let unstake_at = stake(1, 100_000);
+
+                assert_ok!(FractalStaking::unstake_early(Origin::signed(1), 1, unstake_at));
+
+                assert_eq!(Balances::free_balance(&1), 90_000);
+                assert_eq!(Balances::reserved_balance(&1), 0);
+                assert_eq!(staked_balance(1), 0);
+                assert_eq!(distribution_source_balance(), 10_000);
+            });
+        }
+
+        #[test]
+        fn stops_maturing() {
+            run_test(|| {
+                set_penalty(10);
+                let unstake_at = stake(1, 100_000);
+                stake(2, 100_000);
+
+                assert_ok!(FractalStaking::unstake_early(Origin::signed(1), 1, unstake_at));
+
+                assert_eq!(
+                    crate::Maturing::<Test>::get(FractalStaking::release_block(unstake_at))
+                        .into_inner(),
+                    vec![(2, unstake_at)]
+                );
+            });
+        }
+
+        #[test]
+        fn requires_existing_stake() {
+            run_test(|| {
+                set_penalty(10);
+                let unstake_at = stake(1, 100_000);
+
+                assert_noop!(
+                    FractalStaking::unstake_early(Origin::signed(1), 1, unstake_at + 1),
+                    Error::NoStakeUnlockingAt
+                );
+                assert_noop!(
+                    FractalStaking::unstake_early(Origin::signed(2), 2, unstake_at),
+                    Error::NoStakeUnlockingAt
+                );
+            });
+        }
+
+        #[test]
+        fn only_releases_selected_stake() {
+            run_test(|| {
+                set_penalty(10);
+                let first_unstake_at = stake(1, 50_000);
+                step_block();
+                stake(1, 50_000);
+
+                assert_ok!(FractalStaking::unstake_early(Origin::signed(1), 1, first_unstake_at));
+
+                assert_eq!(Balances::free_balance(&1), 45_000);
+                assert_eq!(staked_balance(1), 50_000);
+                assert_eq!(Balances::reserved_balance(&1), 50_000);
+            });
+        }
+
+        #[test]
+        fn penalty_is_distributed_to_remaining_stakers() {
+            run_test(|| {
+                set_penalty(10);
+                let unstake_at = stake(1, 100_000);
+                stake(2, 100_000);
+
+                assert_ok!(FractalStaking::unstake_early(Origin::signed(1), 1, unstake_at));
+                run_to_distribution();
+                claim_rewards(2);
+
+                assert_eq!(staked_balance(1), 0);
+                assert_eq!(staked_balance(2), 110_000);
+            });
+        }
+
+        #[test]
+        fn matured_stake_is_returned_without_penalty() {
+            run_test(|| {
+                let unstake_at = stake(1, 100_000);
+                while System::block_number() < unstake_at {
+                    step_block();
+                }
+
+                assert_ok!(FractalStaking::unstake_early(Origin::signed(1), 1, unstake_at));
+
+                assert_eq!(Balances::free_balance(&1), 100_000);
+                assert_eq!(staked_balance(1), 0);
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod stake_inspector {
+        use super::*;
+        use crate::StakeInspector;
+
+        fn beneficiary() -> u64 {
+            <Test as crate::Config>::DistributionSource::get()
+        }
+
+        fn stake(account: u64, amount: u64) {
+            let _ = Balances::deposit_creating(&account, amount);
+            assert_ok!(FractalStaking::stake(
+                Origin::signed(account),
+                DEFAULT_LOCK,
+                amount
+            ));
+        }
+
+        #[test]
+        fn staked_until_counts_long_enough_locks() {
+            run_test(|| {
+                stake(1, 100_000);
+                let unlocks_at = System::block_number() + DEFAULT_LOCK;
+
+                assert_eq!(FractalStaking::staked_until(&1, unlocks_at), 100_000);
+                assert_eq!(FractalStaking::staked_until(&1, unlocks_at + 1), 0);
+                assert_eq!(FractalStaking::staked_until(&2, unlocks_at), 0);
+            });
+        }
+
+        #[test]
+        fn slash_moves_stake_to_beneficiary() {
+            run_test(|| {
+                set_distribution_source(1);
+                stake(1, 100_000);
+
+                assert_eq!(FractalStaking::slash(&1, 30_000, &beneficiary()), 30_000);
+
+                assert_eq!(staked_balance(1), 70_000);
+                assert_eq!(Balances::reserved_balance(&1), 70_000);
+                assert_eq!(Balances::free_balance(&beneficiary()), 30_001);
+            });
+        }
+
+        #[test]
+        fn slash_is_limited_to_stake() {
+            run_test(|| {
+                set_distribution_source(1);
+                stake(1, 100_000);
+
+                assert_eq!(FractalStaking::slash(&1, 500_000, &beneficiary()), 100_000);
+
+                assert_eq!(staked_balance(1), 0);
+                assert_eq!(crate::StakedAmounts::<Test>::iter_prefix(1).count(), 0);
+            });
+        }
+
+        #[test]
+        fn slashed_stake_does_not_receive_distribution() {
+            run_test(|| {
+                stake(1, 100_000);
+                stake(2, 100_000);
+                set_distribution_source(1);
+                FractalStaking::slash(&1, 50_000, &beneficiary());
+
+                set_distribution_source(150_000);
+                run_to_distribution();
+                claim_rewards(1);
+                claim_rewards(2);
+
+                assert_eq!(staked_balance(1), 100_000);
+                assert_eq!(staked_balance(2), 200_000);
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod delegated_staking {
+        use super::*;
+        use crate::{RollOver, StakeInspector};
+
+        const PAYER: u64 = 1;
+        const BENEFICIARY: u64 = 2;
+        const CONTROLLER: u64 = 3;
+
+        fn stake_for(amount: u64) -> u64 {
+            let _ = Balances::deposit_creating(&PAYER, amount);
+            assert_ok!(FractalStaking::stake_for(
+                Origin::signed(PAYER),
+                BENEFICIARY,
+                CONTROLLER,
+                DEFAULT_LOCK,
+                amount
+            ));
+
+            System::block_number() + DEFAULT_LOCK
+        }
+
+        fn run_past(block: u64) {
+            while System::block_number() <= block {
+                step_block();
+            }
+            run_to_distribution();
+        }
+
+        #[test]
+        fn reserves_from_payer() {
+            run_test(|| {
+                stake_for(100_000);
+
+                assert_eq!(Balances::reserved_balance(&PAYER), 100_000);
+                assert_eq!(Balances::reserved_balance(&BENEFICIARY), 0);
+                assert_eq!(staked_balance(BENEFICIARY), 100_000);
+                assert_eq!(staked_balance(PAYER), 0);
+            });
+        }
+
+        #[test]
+        fn returns_stake_to_payer_and_rewards_to_beneficiary() {
+            run_test(|| {
+                set_distribution_source(100_000);
+                let unstake_at = stake_for(100_000);
+
+                run_past(unstake_at);
+
+                assert_eq!(Balances::free_balance(&PAYER), 100_000);
+                assert_eq!(Balances::reserved_balance(&PAYER), 0);
+                assert_eq!(Balances::free_balance(&BENEFICIARY), 100_000);
+                assert_eq!(crate::DelegatedStakes::<Test>::iter().count(), 0);
+            });
+        }
+
+        #[test]
+        fn only_controller_can_unstake_early() {
+            run_test(|| {
+                assert_ok!(FractalStaking::set_early_unstake_penalty(
+                    Origin::root(),
+                    Some(sp_runtime::Perbill::from_percent(10))
+                ));
+                let unstake_at = stake_for(100_000);
+
+                for account in &[PAYER, BENEFICIARY] {
+                    assert_noop!(
+                        FractalStaking::unstake_early(
+                            Origin::signed(*account),
+                            BENEFICIARY,
+                            unstake_at
+                        ),
+                        Error::NotController
+                    );
+                }
+                assert_ok!(FractalStaking::unstake_early(
+                    Origin::signed(CONTROLLER),
+                    BENEFICIARY,
+                    unstake_at
+                ));
+
+                assert_eq!(Balances::free_balance(&PAYER), 90_000);
+                assert_eq!(Balances::free_balance(&CONTROLLER), 0);
+                assert_eq!(staked_balance(BENEFICIARY), 0);
+            });
+        }
+
+        #[test]
+        fn only_controller_can_roll_over() {
+            run_test(|| {
+                let unstake_at = stake_for(100_000);
+                let roll_over = |compound| {
+                    Some(RollOver {
+                        lock_period: DEFAULT_LOCK,
+                        compound,
+                    })
+                };
+
+                assert_noop!(
+                    FractalStaking::set_roll_over(
+                        Origin::signed(BENEFICIARY),
+                        BENEFICIARY,
+                        unstake_at,
+                        roll_over(false)
+                    ),
+                    Error::NotController
+                );
+                assert_noop!(
+                    FractalStaking::set_roll_over(
+                        Origin::signed(CONTROLLER),
+                        BENEFICIARY,
+                        unstake_at,
+                        roll_over(true)
+                    ),
+                    Error::CannotCompoundDelegatedStake
+                );
+                assert_ok!(FractalStaking::set_roll_over(
+                    Origin::signed(CONTROLLER),
+                    BENEFICIARY,
+                    unstake_at,
+                    roll_over(false)
+                ));
+
+                run_past(unstake_at);
+
+                let new_unstake_at = FractalStaking::release_block(unstake_at) + DEFAULT_LOCK;
+                assert_eq!(Balances::reserved_balance(&PAYER), 100_000);
+                assert_eq!(
+                    FractalStaking::roles(&BENEFICIARY, new_unstake_at).controller,
+                    CONTROLLER
+                );
+            });
+        }
+
+        #[test]
+        fn does_not_merge_stakes_with_different_roles() {
+            run_test(|| {
+                stake_for(50_000);
+
+                let _ = Balances::deposit_creating(&BENEFICIARY, 50_000);
+                assert_noop!(
+                    FractalStaking::stake(Origin::signed(BENEFICIARY), DEFAULT_LOCK, 50_000),
+                    Error::StakeRolesMismatch
+                );
+
+                stake_for(50_000);
+                assert_eq!(staked_balance(BENEFICIARY), 100_000);
+            });
+        }
+
+        #[test]
+        fn slash_takes_from_payer() {
+            run_test(|| {
+                set_distribution_source(1);
+                stake_for(100_000);
+
+                let source = <Test as crate::Config>::DistributionSource::get();
+                assert_eq!(FractalStaking::slash(&BENEFICIARY, 30_000, &source), 30_000);
+
+                assert_eq!(Balances::reserved_balance(&PAYER), 70_000);
+                assert_eq!(staked_balance(BENEFICIARY), 70_000);
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod verified_bonus {
+        use super::*;
+        use sp_runtime::Perbill;
+
+        fn stake(account: u64, amount: u64) {
+            let _ = Balances::deposit_creating(&account, amount);
+            assert_ok!(FractalStaking::stake(
+                Origin::signed(account),
+                DEFAULT_LOCK,
+                amount
+            ));
+        }
+
+        fn set_bonus(percent: u32) {
+            assert_ok!(FractalStaking::set_verified_bonus(
+                Origin::root(),
+                Perbill::from_percent(percent)
+            ));
+        }
+
+        #[test]
+        fn verified_stakes_earn_more() {
+            run_test(|| {
+                set_bonus(50);
+                Verification::set_verified(1, true);
+                stake(1, 100_000);
+                stake(2, 100_000);
+
+                set_distribution_source(250_000);
+                run_to_distribution();
+                claim_rewards(1);
+                claim_rewards(2);
+
+                assert_eq!(staked_balance(1), 100_000 + 150_000);
+                assert_eq!(staked_balance(2), 100_000 + 100_000);
+            });
+        }
+
+        #[test]
+        fn applies_bonus_when_verified() {
+            run_test(|| {
+                set_bonus(50);
+                stake(1, 100_000);
+                stake(2, 100_000);
+                assert_eq!(FractalStaking::effective_total_coin_shares(), 2_000_000);
+
+                Verification::set_verified(1, true);
+
+                assert_eq!(FractalStaking::effective_total_coin_shares(), 2_500_000);
+                assert!(crate::VerifiedStakers::<Test>::contains_key(1));
+            });
+        }
+
+        #[test]
+        fn removes_bonus_when_unverified() {
+            run_test(|| {
+                set_bonus(50);
+                Verification::set_verified(1, true);
+                stake(1, 100_000);
+                stake(2, 100_000);
+                set_distribution_source(250_000);
+                run_to_distribution();
+
+                Verification::set_verified(1, false);
+
+                // Rewards earned with the bonus are added to the stake before it's removed.
+                assert_eq!(Balances::reserved_balance(&1), 100_000 + 150_000);
+                assert_eq!(FractalStaking::effective_total_coin_shares(), 3_500_000);
+
+                set_distribution_source(350_000);
+                run_to_distribution();
+                claim_rewards(1);
+                claim_rewards(2);
+
+                assert_eq!(staked_balance(1), 250_000 + 250_000);
+                assert_eq!(staked_balance(2), 100_000 + 100_000 + 100_000);
+            });
+        }
+
+        #[test]
+        fn new_bonus_applies_to_existing_stakes() {
+            run_test(|| {
+                set_bonus(50);
+                Verification::set_verified(1, true);
+                stake(1, 100_000);
+                stake(2, 100_000);
+
+                set_bonus(100);
+                assert_eq!(FractalStaking::effective_total_coin_shares(), 3_000_000);
+
+                set_distribution_source(300_000);
+                run_to_distribution();
+                claim_rewards(1);
+                claim_rewards(2);
+
+                assert_eq!(staked_balance(1), 100_000 + 200_000);
+                assert_eq!(staked_balance(2), 100_000 + 100_000);
+            });
+        }
+
+        #[test]
+        fn rewards_keep_the_bonus_they_were_distributed_with() {
+            run_test(|| {
+                set_bonus(50);
+                Verification::set_verified(1, true);
+                stake(1, 100_000);
+                stake(2, 100_000);
+                set_distribution_source(250_000);
+                run_to_distribution();
+
+                set_bonus(100);
+                set_distribution_source(300_000);
+                run_to_distribution();
+                claim_rewards(1);
+                claim_rewards(2);
+
+                assert_eq!(staked_balance(1), 100_000 + 150_000 + 200_000);
+                assert_eq!(staked_balance(2), 100_000 + 100_000 + 100_000);
+            });
+        }
+
+        #[test]
+        fn applies_verification_when_requested() {
+            run_test(|| {
+                set_bonus(50);
+                stake(1, 100_000);
+                Verification::set_verified_silently(1, true);
+                assert!(!crate::VerifiedStakers::<Test>::contains_key(1));
+
+                assert_ok!(FractalStaking::apply_bonus(Origin::signed(2), 1));
+
+                assert!(crate::VerifiedStakers::<Test>::contains_key(1));
+                assert_eq!(FractalStaking::effective_total_coin_shares(), 1_500_000);
+            });
+        }
+
+        #[test]
+        fn weight_grows_with_stakes() {
+            use crate::OnVerificationChanged;
+
+            run_test(|| {
+                stake(1, 100_000);
+                stake(2, 100_000);
+                step_block();
+                stake(2, 100_000);
+                Verification::set_verified_silently(1, true);
+                Verification::set_verified_silently(2, true);
+
+                let one_stake = FractalStaking::on_verification_changed(&1);
+                let two_stakes = FractalStaking::on_verification_changed(&2);
+                let unchanged = FractalStaking::on_verification_changed(&2);
+
+                assert!(two_stakes > one_stake);
+                assert!(one_stake > unchanged);
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod events {
+        use super::*;
+        use crate::{Event, RollOver};
+        use sp_runtime::{FixedU128, Perbill};
+
+        fn stake(account: u64, amount: u64) -> u64 {
+            let _ = Balances::deposit_creating(&account, amount);
+            assert_ok!(FractalStaking::stake(
+                Origin::signed(account),
+                DEFAULT_LOCK,
+                amount
+            ));
+
+            System::block_number() + DEFAULT_LOCK
+        }
+
+        #[test]
+        fn stake_emits_staked() {
+            run_test(|| {
+                let unstake_at = stake(1, 100_000);
+
+                assert_eq!(
+                    staking_events().last(),
+                    Some(&Event::Staked {
+                        amount: 100_000,
+                        who: 1,
+                        unstake_at,
+                        payer: 1,
+                        controller: 1,
+                        coin_shares: 1_000_000,
+                    })
+                );
+            });
+        }
+
+        #[test]
+        fn distribution_reports_reward_per_coin_share() {
+            run_test(|| {
+                stake(1, 100_000);
+                set_distribution_source(100_000);
+
+                run_to_distribution();
+
+                assert_eq!(
+                    staking_events().last(),
+                    Some(&Event::Distribution {
+                        amount: 100_000,
+                        reward_per_coin_share: FixedU128::from_inner(100_000_000_000_000_001),
+                    })
+                );
+            });
+        }
+
+        #[test]
+        fn locked_distribution_source_reports_failed_distribution() {
+            use frame_support::traits::{LockableCurrency, WithdrawReasons};
+
+            run_test(|| {
+                stake(1, 100_000);
+                set_distribution_source(100_000);
+                Balances::set_lock(
+                    *b"testlock",
+                    &<Test as crate::Config>::DistributionSource::get(),
+                    100_000,
+                    WithdrawReasons::all(),
+                );
+
+                run_to_distribution();
+
+                assert!(staking_events().contains(&Event::DistributionFailed { amount: 100_000 }));
+                assert_eq!(
+                    crate::RewardPerCoinShare::<Test>::get(),
+                    FixedU128::from_inner(0)
+                );
+            });
+        }
+
+        #[test]
+        fn reports_rewards_paid() {
+            run_test(|| {
+                let unstake_at = stake(1, 100_000);
+                set_distribution_source(100_000);
+
+                step_past_lock_period();
+                run_to_distribution();
+
+                assert!(staking_events().contains(&Event::RewardsPaid {
+                    amount: 100_000,
+                    who: 1,
+                    unstake_at,
+                }));
+                assert!(staking_events().contains(&Event::Unstaked {
+                    amount: 200_000,
+                    who: 1,
+                }));
+            });
+        }
+
+        #[test]
+        fn root_changes_emit_events() {
+            run_test(|| {
+                assert_ok!(FractalStaking::set_minimum_stake(Origin::root(), Some(100)));
+                assert_ok!(FractalStaking::set_early_unstake_penalty(
+                    Origin::root(),
+                    Some(Perbill::from_percent(10))
+                ));
+
+                let events = staking_events();
+                assert_eq!(
+                    &events[events.len() - 2..],
+                    &[
+                        Event::MinimumStakeSet { min: Some(100) },
+                        Event::EarlyUnstakePenaltySet {
+                            penalty: Some(Perbill::from_percent(10)),
+                        },
+                    ]
+                );
+            });
+        }
+
+        #[test]
+        fn set_roll_over_emits_roll_over_set() {
+            run_test(|| {
+                let unstake_at = stake(1, 100_000);
+                let roll_over = Some(RollOver {
+                    lock_period: DEFAULT_LOCK,
+                    compound: false,
+                });
+
+                assert_ok!(FractalStaking::set_roll_over(
+                    Origin::signed(1),
+                    1,
+                    unstake_at,
+                    roll_over.clone()
+                ));
+
+                assert_eq!(
+                    staking_events().last(),
+                    Some(&Event::RollOverSet {
+                        who: 1,
+                        unstake_at,
+                        roll_over,
+                    })
+                );
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod genesis {
+        use super::*;
+
+        fn genesis() -> crate::GenesisConfig<Test> {
+            crate::GenesisConfig {
+                lock_period_shares: vec![(DEFAULT_LOCK, 10), (300, 30)],
+                minimum_stake: Some(1_000),
+            }
+        }
+
+        #[test]
+        fn sets_tiers_and_minimum_stake() {
+            genesis_test_ext(genesis()).execute_with(|| {
+                assert_eq!(
+                    FractalStaking::lock_period_tiers(),
+                    vec![(DEFAULT_LOCK, 10), (300, 30)]
+                );
+                assert_eq!(crate::MinimumStake::<Test>::get(), Some(1_000));
+                assert_eq!(
+                    crate::pallet::StorageVersion::<Test>::get(),
+                    crate::Releases::V3
+                );
+            });
+        }
+
+        #[test]
+        fn accepts_stakes_from_first_block() {
+            genesis_test_ext(genesis()).execute_with(|| {
+                step_block();
+                let _ = Balances::deposit_creating(&1, 100_000);
+
+                assert_noop!(
+                    FractalStaking::stake(Origin::signed(1), 300, 999),
+                    Error::AmountBelowMinimum
+                );
+                assert_ok!(FractalStaking::stake(Origin::signed(1), 300, 1_000));
+                assert_eq!(staked_balance(1), 1_000);
+
+                check_invariants();
+            });
+        }
+    }
 }
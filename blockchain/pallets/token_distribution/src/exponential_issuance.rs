@@ -1,8 +1,89 @@
-use core::f64::consts::LN_2;
 use num_traits::{cast, zero};
+use sp_arithmetic::{
+    traits::{One, Saturating, Zero},
+    FixedPointNumber, FixedU128,
+};
+
+/// Binary digits of `index / half_life` used when computing the exponential curve. Digits past
+/// this change `FixedU128` by less than its precision.
+const FRACTION_BITS: usize = 60;
+
+/// `2^(-2^-i)` for `i` in `1..=FRACTION_BITS`, as the inner values of `FixedU128` rounded to
+/// nearest.
+const HALVING_TABLE: [u128; FRACTION_BITS] = [
+    707106781186547524,
+    840896415253714543,
+    917004043204671232,
+    957603280698573647,
+    978572062087700135,
+    989228013193975484,
+    994599423483633176,
+    997296056085470126,
+    998647112890970174,
+    999323327502650752,
+    999661606496243684,
+    999830788931929063,
+    999915390886613498,
+    999957694548431133,
+    999978847050491930,
+    999989423469314464,
+    999994711720674283,
+    999997355856841395,
+    999998677927546760,
+    999999338963554895,
+    999999669481722826,
+    999999834740847758,
+    999999917370420465,
+    999999958685209379,
+    999999979342604476,
+    999999989671302185,
+    999999994835651079,
+    999999997417825536,
+    999999998708912767,
+    999999999354456383,
+    999999999677228192,
+    999999999838614096,
+    999999999919307048,
+    999999999959653524,
+    999999999979826762,
+    999999999989913381,
+    999999999994956690,
+    999999999997478345,
+    999999999998739173,
+    999999999999369586,
+    999999999999684793,
+    999999999999842397,
+    999999999999921198,
+    999999999999960599,
+    999999999999980300,
+    999999999999990150,
+    999999999999995075,
+    999999999999997537,
+    999999999999998769,
+    999999999999999384,
+    999999999999999692,
+    999999999999999846,
+    999999999999999923,
+    999999999999999962,
+    999999999999999981,
+    999999999999999990,
+    999999999999999995,
+    999999999999999998,
+    999999999999999999,
+    999999999999999999,
+];
+
+/// `ln 2` as the inner value of a `FixedU128`.
+const LN_2: u128 = 693147180559945309;
+
+/// `e * ln 2` as the inner value of a `FixedU128`.
+const E_LN_2: u128 = 1884169385363720110;
 
 /// Exponential issuance curve in unit-less steps with a linear portion to
 /// complete issuance at step `complete_at`.
+///
+/// Computed with integer and fixed point arithmetic only, so every platform agrees on it bit for
+/// bit. The amount unissued at each step is within `total / 10^14 + 1` of the exact curve.
 #[derive(Debug, Clone)]
 pub struct Issuance<Balance = u128, Step = u64> {
     pub total: Balance,
@@ -27,25 +108,152 @@ where
             return self.total;
         }
 
-        let total: f64 = cast(self.total).unwrap();
-        cast(self.percent_unissued_at(index) * total).unwrap()
+        let total: u128 = cast(self.total).unwrap();
+        cast(self.portion_unissued_at(index).saturating_mul_int(total)).unwrap()
     }
 
-    // Exponential curve with a half-life of h = e^(-x * ln2 / h).
+    // Exponential curve with a half-life of h = 2^(-x / h).
     // Linear portion is tangent to the curve such that the line equals zero at
     // `complete_at`.
-    fn percent_unissued_at(&self, index: Step) -> f64 {
-        let complete_at: f64 = cast(self.complete_at).unwrap();
-        let half_life: f64 = cast(self.half_life).unwrap();
-        let index: f64 = cast(index).unwrap();
+    fn portion_unissued_at(&self, index: Step) -> FixedU128 {
+        let complete_at: u128 = cast(self.complete_at).unwrap();
+        let half_life: u128 = cast(self.half_life).unwrap();
+        let index: u128 = cast(index).unwrap();
+
+        if half_life == 0 {
+            return FixedU128::zero();
+        }
+
+        // The line starts at `t = complete_at - h / ln2`, so covers `index` if
+        // `(complete_at - index) * ln2 < h`.
+        let until_complete = complete_at - index;
+        if FixedU128::from_inner(LN_2).saturating_mul_int(until_complete) < half_life {
+            // The line is `2^(-t / h) * ln2 * (complete_at - index) / h`, and
+            // `2^(-t / h) = e * 2^(-complete_at / h)`.
+            FixedU128::from_inner(E_LN_2)
+                .saturating_mul(pow2_neg(complete_at, half_life))
+                .saturating_mul(FixedU128::saturating_from_rational(until_complete, half_life))
+        } else {
+            pow2_neg(index, half_life)
+        }
+    }
+}
+
+/// `2^(-index / half_life)`, multiplying together the `HALVING_TABLE` entries of the binary
+/// digits of the fractional part.
+fn pow2_neg(index: u128, half_life: u128) -> FixedU128 {
+    let halvings = index / half_life;
+    if halvings >= 128 {
+        return FixedU128::zero();
+    }
+
+    let mut remainder = index % half_life;
+    let mut result = FixedU128::one();
+    for halving in HALVING_TABLE.iter() {
+        // Doubles `remainder` without overflowing, carrying the next digit into `result`.
+        let to_half_life = half_life - remainder;
+        if remainder >= to_half_life {
+            remainder -= to_half_life;
+            result = result.saturating_mul(FixedU128::from_inner(*halving));
+        } else {
+            remainder *= 2;
+        }
+    }
+
+    FixedU128::from_inner(result.into_inner() >> halvings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::LN_2;
+    use quickcheck::TestResult;
+
+    /// The previous floating point implementation, which could differ between CPUs.
+    fn reference_unissued_at(issuance: &Issuance<u64, u32>, index: u32) -> u64 {
+        if index >= issuance.complete_at {
+            return 0;
+        }
+        if index == 0 {
+            return issuance.total;
+        }
+
+        let complete_at = issuance.complete_at as f64;
+        let half_life = issuance.half_life as f64;
+        let index = index as f64;
 
         let linear_after = complete_at - half_life / LN_2;
 
-        let e = |x: f64| libm::exp(-x * LN_2 / half_life);
-        if index > linear_after {
+        let e = |x: f64| (-x * LN_2 / half_life).exp();
+        let percent = if index > linear_after {
             e(linear_after) - LN_2 / half_life * e(linear_after) * (index - linear_after)
         } else {
             e(index)
+        };
+        (percent * issuance.total as f64) as u64
+    }
+
+    fn runtime_issuance() -> Issuance<u128, u32> {
+        const YEARS: u32 = 10 * 60 * 24 * 365;
+
+        Issuance {
+            total: 400_000_000 * 1_000_000_000_000,
+            half_life: 10 * YEARS,
+            complete_at: 120 * YEARS,
         }
     }
+
+    #[quickcheck]
+    fn matches_f64_reference(
+        total: u64,
+        half_life: u32,
+        complete_at: u32,
+        index: u32,
+    ) -> TestResult {
+        if half_life == 0 {
+            return TestResult::discard();
+        }
+
+        let issuance = Issuance {
+            total,
+            half_life,
+            complete_at,
+        };
+        let fixed = issuance.total_issued_by(index);
+        let reference = total - reference_unissued_at(&issuance, index);
+
+        // The bound documented on `Issuance`. `f64` itself is only accurate to about
+        // `total / 10^16`, well within it.
+        let tolerance = total / 100_000_000_000_000 + 1;
+        TestResult::from_bool(fixed.max(reference) - fixed.min(reference) <= tolerance)
+    }
+
+    #[quickcheck]
+    fn never_decreases_with_runtime_parameters(index: u32) -> bool {
+        let issuance = runtime_issuance();
+        let index = index % (issuance.complete_at + 1);
+
+        issuance.total_issued_by(index) <= issuance.total_issued_by(index + 1)
+    }
+
+    #[test]
+    fn halves_at_half_life() {
+        let issuance = runtime_issuance();
+
+        assert_eq!(issuance.total_issued_by(issuance.half_life), issuance.total / 2);
+        assert_eq!(issuance.total_issued_by(issuance.half_life * 2), issuance.total / 4 * 3);
+    }
+
+    #[test]
+    fn completes_at_complete_at() {
+        let issuance = runtime_issuance();
+
+        assert!(issuance.total_issued_by(issuance.complete_at - 1) < issuance.total);
+        assert_eq!(issuance.total_issued_by(issuance.complete_at), issuance.total);
+    }
+
+    #[test]
+    fn issues_nothing_at_start() {
+        assert_eq!(runtime_issuance().total_issued_by(0), 0);
+    }
 }
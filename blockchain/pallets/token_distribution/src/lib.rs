@@ -2,6 +2,12 @@
 
 pub use pallet::*;
 
+pub mod migrations;
+
+#[cfg(test)]
+#[macro_use]
+extern crate quickcheck_macros;
+
 #[cfg(test)]
 mod mock;
 
@@ -17,13 +23,20 @@ pub mod pallet {
     use frame_system::pallet_prelude::*;
 
     use frame_support::{
-        sp_runtime::traits::Saturating,
-        traits::{Currency, Get},
+        inherent::Vec,
+        sp_runtime::{
+            traits::{CheckedAdd, Convert, Saturating, Zero},
+            Perbill,
+        },
+        traits::{Currency, Get, Imbalance},
+        weights::Weight,
     };
+    #[cfg(feature = "std")]
+    use serde::{Deserialize, Serialize};
 
     pub type FractalId = u64;
 
-    type BalanceOf<T> =
+    pub(crate) type BalanceOf<T> =
         <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
     #[pallet::config]
@@ -35,6 +48,12 @@ pub mod pallet {
         type TotalIssuance: Get<BalanceOf<Self>>;
         type IssuanceHalfLife: Get<Self::BlockNumber>;
         type IssuanceCompleteAt: Get<Self::BlockNumber>;
+
+        /// Longest address or swap id `burn_to` accepts.
+        type MaxBurnTargetLength: Get<u32>;
+
+        /// Accounts that issuance to each `Purpose` is paid to.
+        type PurposeAccounts: Convert<Purpose, Self::AccountId>;
     }
 
     #[pallet::storage]
@@ -47,35 +66,262 @@ pub mod pallet {
     pub type AllowedToMint<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, (), OptionQuery>;
 
+    /// How much each account in `AllowedToMint` may mint. Accounts without one can't mint.
+    #[pallet::storage]
+    pub type MintQuotas<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        MintQuota<BalanceOf<T>, BlockNumberFor<T>>,
+        OptionQuery,
+    >;
+
+    /// How much each account in `AllowedToMint` has minted against its `MintQuotas`.
+    #[pallet::storage]
+    pub type MintedBy<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        MintUsage<BalanceOf<T>, BlockNumberFor<T>>,
+        ValueQuery,
+    >;
+
     #[pallet::storage]
     pub type DestinationWeights<T: Config> =
-        StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+        StorageMap<_, Blake2_128Concat, Destination<T::AccountId>, u32, ValueQuery>;
+
+    /// Nonce of the next `burn_to`.
+    #[pallet::storage]
+    pub type NextBurnNonce<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+    #[pallet::storage]
+    pub type Burns<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        u64,
+        BurnRecord<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+        OptionQuery,
+    >;
+
+    #[pallet::storage]
+    pub(super) type StorageVersion<T: Config> = StorageValue<_, Releases, ValueQuery>;
+
+    /// Use of issuance, paid to the account `PurposeAccounts` gives for it.
+    #[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    pub enum Purpose {
+        DataCaptureRewards,
+        StakingRewards,
+    }
+
+    impl Purpose {
+        pub const ALL: [Purpose; 2] = [Purpose::DataCaptureRewards, Purpose::StakingRewards];
+    }
+
+    /// Receiver of a share of issuance.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    pub enum Destination<AccountId> {
+        Address(AccountId),
+        Purpose(Purpose),
+    }
+
+    /// Where tokens burned with `burn_to` should be paid out by a bridge.
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    pub enum BurnTarget {
+        /// An address on the destination chain, in that chain's encoding.
+        Address(Vec<u8>),
+        /// An id the bridge has matched to a swap.
+        SwapId(Vec<u8>),
+    }
+
+    impl BurnTarget {
+        fn len(&self) -> usize {
+            match self {
+                BurnTarget::Address(bytes) | BurnTarget::SwapId(bytes) => bytes.len(),
+            }
+        }
+    }
+
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq, Default)]
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+    pub struct MintQuota<Balance, BlockNumber> {
+        /// Most the minter can mint in total.
+        pub total_cap: Balance,
+        /// Most the minter can have minted recently. What it mints counts against this, and is
+        /// paid back at `period_allowance` every `period` blocks, so the allowance rolls rather
+        /// than resetting at fixed times.
+        pub period_allowance: Balance,
+        /// Blocks it takes to pay back a full `period_allowance`.
+        pub period: BlockNumber,
+    }
+
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq, Default)]
+    pub struct MintUsage<Balance, BlockNumber> {
+        pub total: Balance,
+        /// Amount still counting against `period_allowance`, as of `updated_at`.
+        pub recent: Balance,
+        pub updated_at: BlockNumber,
+    }
+
+    #[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+    pub struct BurnRecord<AccountId, Balance, BlockNumber> {
+        pub from: AccountId,
+        pub amount: Balance,
+        /// Bridge's identifier of the chain to pay out on.
+        pub chain: u64,
+        pub target: BurnTarget,
+        pub block: BlockNumber,
+    }
+
+    #[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Releases {
+        V1,
+        /// `DestinationWeights` is keyed by `Destination`.
+        V2,
+        /// Every account in `AllowedToMint` had `MintQuotas` when they were introduced.
+        V3,
+    }
+
+    impl Default for Releases {
+        fn default() -> Self {
+            Releases::V1
+        }
+    }
+
+    #[pallet::genesis_config]
+    pub struct GenesisConfig<T: Config> {
+        pub destination_weights: Vec<(Destination<T::AccountId>, u32)>,
+        pub allowed_to_mint: Vec<(T::AccountId, MintQuota<BalanceOf<T>, T::BlockNumber>)>,
+    }
+
+    #[cfg(feature = "std")]
+    impl<T: Config> Default for GenesisConfig<T> {
+        fn default() -> Self {
+            GenesisConfig {
+                destination_weights: Vec::new(),
+                allowed_to_mint: Vec::new(),
+            }
+        }
+    }
+
+    #[pallet::genesis_build]
+    impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+        fn build(&self) {
+            StorageVersion::<T>::put(Releases::V3);
+
+            for (destination, weight) in &self.destination_weights {
+                DestinationWeights::<T>::insert(destination, weight);
+            }
+
+            for (address, quota) in &self.allowed_to_mint {
+                AllowedToMint::<T>::insert(address, ());
+                MintQuotas::<T>::insert(address, quota);
+            }
+        }
+    }
 
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
     pub struct Pallet<T>(_);
 
     #[pallet::event]
-    #[pallet::metadata(BalanceOf<T> = "Balance")]
-    pub enum Event<T: Config> {}
+    #[pallet::metadata(BalanceOf<T> = "Balance", T::AccountId = "AccountId")]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// A non-zero `amount` of the issuance curve was deposited to `destination` at the end of a
+        /// block.
+        Issued {
+            destination: T::AccountId,
+            amount: BalanceOf<T>,
+        },
+        /// `amount` was minted to `to`, leaving `ArtificiallyIssued` and `ArtificiallyBurned` at
+        /// `artificially_issued` and `artificially_burned`.
+        Minted {
+            to: T::AccountId,
+            amount: BalanceOf<T>,
+            artificially_issued: BalanceOf<T>,
+            artificially_burned: BalanceOf<T>,
+        },
+        /// `amount` was burned from `from`, leaving `ArtificiallyIssued` and `ArtificiallyBurned`
+        /// at `artificially_issued` and `artificially_burned`.
+        Burned {
+            from: T::AccountId,
+            amount: BalanceOf<T>,
+            artificially_issued: BalanceOf<T>,
+            artificially_burned: BalanceOf<T>,
+        },
+        /// `amount` issued outside of this pallet was accounted for.
+        ArtificiallyIssuedIncremented {
+            amount: BalanceOf<T>,
+            artificially_issued: BalanceOf<T>,
+            artificially_burned: BalanceOf<T>,
+        },
+        WeightSet {
+            destination: Destination<T::AccountId>,
+            weight: u32,
+            previous: u32,
+        },
+        AllowMintingSet {
+            address: T::AccountId,
+            allowed: bool,
+        },
+        /// `amount` was burned from `from` to be paid out to `target` on `chain`, recorded in
+        /// `Burns` under `nonce`.
+        BurnedTo {
+            nonce: u64,
+            from: T::AccountId,
+            amount: BalanceOf<T>,
+            chain: u64,
+            target: BurnTarget,
+            artificially_issued: BalanceOf<T>,
+            artificially_burned: BalanceOf<T>,
+        },
+        MintQuotaSet {
+            address: T::AccountId,
+            quota: MintQuota<BalanceOf<T>, T::BlockNumber>,
+        },
+    }
 
     #[pallet::error]
-    pub enum Error<T> {}
+    pub enum Error<T> {
+        /// Tried to burn more than the account's free balance.
+        InsufficientBalance,
+        NothingToBurn,
+        /// `burn_to`'s target is longer than `MaxBurnTargetLength`.
+        BurnTargetTooLong,
+        /// Minting would take the minter past its `MintQuotas` total cap.
+        MintTotalCapExceeded,
+        /// Minting would take the minter past its `MintQuotas` allowance for the last `period`
+        /// blocks.
+        MintPeriodAllowanceExceeded,
+        /// The minter is in `AllowedToMint` but has no `MintQuotas` set.
+        NoMintQuota,
+    }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         #[pallet::weight((
-            10_000 + T::DbWeight::get().reads_writes(0, 1),
+            10_000 + T::DbWeight::get().reads_writes(1, 1),
             DispatchClass::Normal,
             Pays::No
         ))]
         pub fn set_weight(
             origin: OriginFor<T>,
-            address: T::AccountId,
+            destination: Destination<T::AccountId>,
             #[pallet::compact] weight: u32,
         ) -> DispatchResult {
             ensure_root(origin)?;
-            DestinationWeights::<T>::insert(address, weight);
+            let previous =
+                DestinationWeights::<T>::mutate(&destination, |w| core::mem::replace(w, weight));
+
+            Self::deposit_event(Event::<T>::WeightSet {
+                destination,
+                weight,
+                previous,
+            });
+
             Ok(())
         }
 
@@ -90,6 +336,13 @@ pub mod pallet {
         ) -> DispatchResult {
             ensure_root(origin)?;
             Self::increase_artificial(amount);
+
+            Self::deposit_event(Event::<T>::ArtificiallyIssuedIncremented {
+                amount,
+                artificially_issued: ArtificiallyIssued::<T>::get(),
+                artificially_burned: ArtificiallyBurned::<T>::get(),
+            });
+
             Ok(())
         }
 
@@ -106,16 +359,23 @@ pub mod pallet {
             ensure_root(origin)?;
 
             if should_allow {
-                AllowedToMint::<T>::insert(address, ());
+                AllowedToMint::<T>::insert(&address, ());
             } else {
-                AllowedToMint::<T>::remove(address);
+                AllowedToMint::<T>::remove(&address);
             }
 
+            Self::deposit_event(Event::<T>::AllowMintingSet {
+                address,
+                allowed: should_allow,
+            });
+
             Ok(())
         }
 
+        /// Mints `amount` to `address`. Signed origins must be in `AllowedToMint`, and count
+        /// `amount` against their `MintQuotas`.
         #[pallet::weight((
-            10_000 + T::DbWeight::get().reads_writes(1, 1),
+            10_000 + T::DbWeight::get().reads_writes(4, 4),
             DispatchClass::Normal,
             Pays::No
         ))]
@@ -124,31 +384,186 @@ pub mod pallet {
             address: T::AccountId,
             #[pallet::compact] amount: BalanceOf<T>,
         ) -> DispatchResult {
-            Self::ensure_allowed_to_mint(origin)?;
+            Self::ensure_allowed_to_mint(origin, amount)?;
 
             T::Currency::deposit_creating(&address, amount);
             Self::increase_artificial(amount);
 
+            Self::deposit_event(Event::<T>::Minted {
+                to: address,
+                amount,
+                artificially_issued: ArtificiallyIssued::<T>::get(),
+                artificially_burned: ArtificiallyBurned::<T>::get(),
+            });
+
             Ok(())
         }
 
+        /// Burns `amount` of the sender's free balance, or all of it if `None`.
         #[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
         pub fn burn(origin: OriginFor<T>, amount: Option<BalanceOf<T>>) -> DispatchResult {
             let address = ensure_signed(origin)?;
 
-            let slash = match amount {
-                Some(amount) => core::cmp::min(amount, T::Currency::free_balance(&address)),
-                None => T::Currency::free_balance(&address),
-            };
-            T::Currency::slash(&address, slash);
+            let amount = Self::do_burn(&address, amount)?;
+
+            Self::deposit_event(Event::<T>::Burned {
+                from: address,
+                amount,
+                artificially_issued: ArtificiallyIssued::<T>::get(),
+                artificially_burned: ArtificiallyBurned::<T>::get(),
+            });
+
+            Ok(())
+        }
+
+        /// Burns like `burn`, recording that a bridge should pay it out to `target` on `chain`.
+        #[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 5))]
+        pub fn burn_to(
+            origin: OriginFor<T>,
+            amount: Option<BalanceOf<T>>,
+            chain: u64,
+            target: BurnTarget,
+        ) -> DispatchResult {
+            let address = ensure_signed(origin)?;
+
+            ensure!(
+                target.len() <= T::MaxBurnTargetLength::get() as usize,
+                Error::<T>::BurnTargetTooLong
+            );
 
-            Self::decrease_artificial(slash);
+            let amount = Self::do_burn(&address, amount)?;
+
+            let nonce = NextBurnNonce::<T>::mutate(|n| {
+                let nonce = *n;
+                *n += 1;
+                nonce
+            });
+            Burns::<T>::insert(
+                nonce,
+                BurnRecord {
+                    from: address.clone(),
+                    amount,
+                    chain,
+                    target: target.clone(),
+                    block: frame_system::Pallet::<T>::block_number(),
+                },
+            );
+
+            Self::deposit_event(Event::<T>::BurnedTo {
+                nonce,
+                from: address,
+                amount,
+                chain,
+                target,
+                artificially_issued: ArtificiallyIssued::<T>::get(),
+                artificially_burned: ArtificiallyBurned::<T>::get(),
+            });
+
+            Ok(())
+        }
+
+        /// Sets how much `address` may mint if it's in `AllowedToMint`. What it has already minted
+        /// still counts against the new quota.
+        #[pallet::weight((
+            10_000 + T::DbWeight::get().reads_writes(0, 1),
+            DispatchClass::Normal,
+            Pays::No
+        ))]
+        pub fn set_mint_quota(
+            origin: OriginFor<T>,
+            address: T::AccountId,
+            quota: MintQuota<BalanceOf<T>, BlockNumberFor<T>>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            MintQuotas::<T>::insert(&address, &quota);
+
+            Self::deposit_event(Event::<T>::MintQuotaSet { address, quota });
 
             Ok(())
         }
     }
 
     impl<T: Config> Pallet<T> {
+        /// Account that issuance to `destination` is paid to.
+        pub fn account_of(destination: Destination<T::AccountId>) -> T::AccountId {
+            match destination {
+                Destination::Address(address) => address,
+                Destination::Purpose(purpose) => T::PurposeAccounts::convert(purpose),
+            }
+        }
+
+        /// Most `who` could mint in the current block under its `MintQuotas`.
+        pub fn mint_allowance(who: &T::AccountId) -> BalanceOf<T> {
+            let quota = match MintQuotas::<T>::get(who) {
+                Some(quota) => quota,
+                None => return Zero::zero(),
+            };
+            let usage = Self::current_usage(who, &quota);
+
+            let total_left = quota.total_cap.saturating_sub(usage.total);
+            let period_left = quota.period_allowance.saturating_sub(usage.recent);
+            total_left.min(period_left)
+        }
+
+        /// `who`'s `MintedBy` as of the current block, with what has been paid back since it was
+        /// last updated taken off `recent`.
+        fn current_usage(
+            who: &T::AccountId,
+            quota: &MintQuota<BalanceOf<T>, BlockNumberFor<T>>,
+        ) -> MintUsage<BalanceOf<T>, BlockNumberFor<T>> {
+            let mut usage = MintedBy::<T>::get(who);
+
+            let now = frame_system::Pallet::<T>::block_number();
+            let elapsed = now.saturating_sub(usage.updated_at);
+            let paid_back = if elapsed >= quota.period {
+                usage.recent
+            } else {
+                Perbill::from_rational_approximation(elapsed, quota.period)
+                    * quota.period_allowance
+            };
+            usage.recent = usage.recent.saturating_sub(paid_back);
+            usage.updated_at = now;
+
+            usage
+        }
+
+        fn use_mint_quota(who: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+            let quota = MintQuotas::<T>::get(who).ok_or(Error::<T>::NoMintQuota)?;
+            let mut usage = Self::current_usage(who, &quota);
+
+            usage.total = usage
+                .total
+                .checked_add(&amount)
+                .filter(|total| *total <= quota.total_cap)
+                .ok_or(Error::<T>::MintTotalCapExceeded)?;
+            usage.recent = usage
+                .recent
+                .checked_add(&amount)
+                .filter(|recent| *recent <= quota.period_allowance)
+                .ok_or(Error::<T>::MintPeriodAllowanceExceeded)?;
+
+            MintedBy::<T>::insert(who, usage);
+            Ok(())
+        }
+
+        /// Burns `amount` of `who`'s free balance, or all of it if `None`, returning the amount
+        /// burned.
+        fn do_burn(
+            who: &T::AccountId,
+            amount: Option<BalanceOf<T>>,
+        ) -> Result<BalanceOf<T>, DispatchError> {
+            let free = T::Currency::free_balance(who);
+            let amount = amount.unwrap_or(free);
+            ensure!(amount <= free, Error::<T>::InsufficientBalance);
+            ensure!(!amount.is_zero(), Error::<T>::NothingToBurn);
+
+            T::Currency::slash(who, amount);
+            Self::decrease_artificial(amount);
+
+            Ok(amount)
+        }
+
         fn increase_artificial(amount: BalanceOf<T>) {
             let after_from = ArtificiallyBurned::<T>::mutate(|a| {
                 let orig_a = *a;
@@ -178,14 +593,14 @@ pub mod pallet {
             should_be_issued.saturating_sub(already_issued)
         }
 
-        fn ensure_allowed_to_mint(origin: OriginFor<T>) -> DispatchResult {
+        fn ensure_allowed_to_mint(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
             if let Ok(()) = ensure_root(origin.clone()) {
                 return Ok(());
             }
 
             let signed_by = ensure_signed(origin)?;
-            if let Some(()) = AllowedToMint::<T>::get(signed_by) {
-                return Ok(());
+            if let Some(()) = AllowedToMint::<T>::get(&signed_by) {
+                return Self::use_mint_quota(&signed_by, amount);
             }
 
             Err(DispatchError::BadOrigin)
@@ -204,13 +619,6 @@ pub mod pallet {
                 return;
             }
 
-            // Using Issuance like this makes it _technically_ possible for
-            // consensus to fail if the CPU's floating point calculations are
-            // different.
-            //
-            // If this becomes a problem, we can have this value derived from
-            // an extrinsic that an authoritative account sets. Similar to how
-            // the timestamp pallet works.
             let issuance = crate::Issuance {
                 total: T::TotalIssuance::get(),
                 half_life: T::IssuanceHalfLife::get(),
@@ -220,10 +628,23 @@ pub mod pallet {
             let needed = Self::needed_to_reach(issuance.total_issued_by(block_number));
             let unit_balance = needed / total_weight.into();
 
-            for (address, weight) in DestinationWeights::<T>::iter() {
+            for (destination, weight) in DestinationWeights::<T>::iter() {
+                let address = Self::account_of(destination);
                 let to_this = unit_balance * weight.into();
-                T::Currency::deposit_creating(&address, to_this);
+                let amount = T::Currency::deposit_creating(&address, to_this).peek();
+                if amount.is_zero() {
+                    continue;
+                }
+
+                Self::deposit_event(Event::<T>::Issued {
+                    destination: address,
+                    amount,
+                });
             }
         }
+
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::migrate::<T>()
+        }
     }
 }
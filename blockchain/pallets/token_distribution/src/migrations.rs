@@ -0,0 +1,87 @@
+use crate::pallet::{
+    AllowedToMint, BalanceOf, Config, Destination, DestinationWeights, MintQuota, MintQuotas,
+    Pallet, Purpose, Releases, StorageVersion,
+};
+use frame_support::{
+    inherent::Vec,
+    sp_runtime::traits::{Bounded, Convert, Zero},
+    storage::migration::storage_key_iter,
+    traits::{Get, PalletInfo},
+    weights::Weight,
+    Blake2_128Concat,
+};
+
+pub fn migrate<T: Config>() -> Weight {
+    let mut weight = T::DbWeight::get().reads(1);
+
+    if StorageVersion::<T>::get() < Releases::V2 {
+        weight = weight.saturating_add(v2::migrate::<T>());
+        StorageVersion::<T>::put(Releases::V2);
+    }
+    if StorageVersion::<T>::get() < Releases::V3 {
+        weight = weight.saturating_add(v3::migrate::<T>());
+        StorageVersion::<T>::put(Releases::V3);
+    }
+
+    weight
+}
+
+/// Keys `DestinationWeights` by `Destination` rather than `AccountId`. Accounts that
+/// `PurposeAccounts` gives for a purpose become that purpose, so they follow the runtime's
+/// configuration from then on.
+pub mod v2 {
+    use super::*;
+
+    pub fn migrate<T: Config>() -> Weight {
+        let pallet = <T as frame_system::Config>::PalletInfo::name::<Pallet<T>>()
+            .expect("pallet is part of the runtime");
+
+        // Collected before inserting, since the new keys share the old ones' prefix.
+        let weights = storage_key_iter::<T::AccountId, u32, Blake2_128Concat>(
+            pallet.as_bytes(),
+            b"DestinationWeights",
+        )
+        .drain()
+        .collect::<Vec<_>>();
+        let count = weights.len() as Weight;
+
+        for (address, weight) in weights {
+            let destination = Purpose::ALL
+                .iter()
+                .find(|purpose| T::PurposeAccounts::convert(**purpose) == address)
+                .map(|purpose| Destination::Purpose(*purpose))
+                .unwrap_or(Destination::Address(address));
+
+            DestinationWeights::<T>::mutate(destination, |w| *w = w.saturating_add(weight));
+        }
+
+        T::DbWeight::get().reads_writes(count, count * 2)
+    }
+}
+
+/// Gives accounts already in `AllowedToMint` an unlimited quota, so they keep minting as they did
+/// before `MintQuotas` until root sets theirs with `set_mint_quota`.
+pub mod v3 {
+    use super::*;
+
+    pub fn migrate<T: Config>() -> Weight {
+        let minters = AllowedToMint::<T>::iter()
+            .map(|(address, ())| address)
+            .filter(|address| !MintQuotas::<T>::contains_key(address))
+            .collect::<Vec<_>>();
+        let count = minters.len() as Weight;
+
+        for address in minters {
+            MintQuotas::<T>::insert(
+                address,
+                MintQuota::<BalanceOf<T>, T::BlockNumber> {
+                    total_cap: BalanceOf::<T>::max_value(),
+                    period_allowance: BalanceOf::<T>::max_value(),
+                    period: Zero::zero(),
+                },
+            );
+        }
+
+        T::DbWeight::get().reads_writes(count * 2, count)
+    }
+}
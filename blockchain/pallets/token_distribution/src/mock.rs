@@ -1,10 +1,11 @@
 use crate as fractal_token_distribution;
+use crate::Purpose;
 use frame_support::parameter_types;
 use frame_system as system;
 use sp_core::H256;
 use sp_runtime::{
     testing::Header,
-    traits::{BlakeTwo256, IdentityLookup},
+    traits::{BlakeTwo256, Convert, IdentityLookup},
     BuildStorage,
 };
 
@@ -20,7 +21,7 @@ frame_support::construct_runtime!(
     {
         System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
         Balances: pallet_balances::{Pallet, Call, Storage, Event<T>},
-        FractalTokenDistribution: fractal_token_distribution::{Pallet, Call, Storage, Event<T>},
+        FractalTokenDistribution: fractal_token_distribution::{Pallet, Call, Storage, Config<T>, Event<T>},
     }
 );
 
@@ -75,6 +76,7 @@ parameter_types! {
     pub const TotalIssuance: u64 = 420_000_000;
     pub const IssuanceHalfLife: u64 = 600;
     pub const IssuanceCompleteAt: u64 = 10_000;
+    pub const MaxBurnTargetLength: u32 = 64;
     pub const MaxRewardPerUser: u64 = 420_000;
 
     pub const ExcessMintingReceiver: u64 = 1234;
@@ -86,10 +88,35 @@ impl fractal_token_distribution::Config for Test {
     type TotalIssuance = TotalIssuance;
     type IssuanceHalfLife = IssuanceHalfLife;
     type IssuanceCompleteAt = IssuanceCompleteAt;
+    type MaxBurnTargetLength = MaxBurnTargetLength;
+    type PurposeAccounts = PurposeAccounts;
+}
+
+parameter_types! {
+    pub const DataCaptureAccount: u64 = 1001;
+    pub const StakingAccount: u64 = 1002;
+}
+
+pub struct PurposeAccounts;
+
+impl Convert<Purpose, u64> for PurposeAccounts {
+    fn convert(purpose: Purpose) -> u64 {
+        match purpose {
+            Purpose::DataCaptureRewards => DataCaptureAccount::get(),
+            Purpose::StakingRewards => StakingAccount::get(),
+        }
+    }
 }
 
 pub fn new_test_ext() -> sp_io::TestExternalities {
+    genesis_test_ext(Default::default())
+}
+
+pub fn genesis_test_ext(
+    fractal_token_distribution: fractal_token_distribution::GenesisConfig<Test>,
+) -> sp_io::TestExternalities {
     GenesisConfig {
+        fractal_token_distribution,
         ..Default::default()
     }
     .build_storage()
@@ -1,4 +1,5 @@
-use crate::mock::*;
+use crate::{mock::*, Destination::Address};
+use core::convert::TryInto;
 use frame_support::{
     assert_noop, assert_ok,
     traits::{
@@ -28,6 +29,13 @@ mod token_distribution {
         FractalTokenDistribution::on_initialize(System::block_number());
     }
 
+    fn distribution_events() -> Vec<crate::Event<Test>> {
+        System::events()
+            .into_iter()
+            .filter_map(|record| record.event.try_into().ok())
+            .collect()
+    }
+
     #[cfg(test)]
     mod per_block {
         use super::*;
@@ -35,7 +43,7 @@ mod token_distribution {
         #[test]
         fn distributes_to_single_address() {
             run_test(|| {
-                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), 42, 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
                 step_block();
 
                 assert_eq!(Balances::free_balance(&42), FIRST_MINTING_TOTAL);
@@ -45,9 +53,9 @@ mod token_distribution {
         #[test]
         fn distributes_among_many_addresses() {
             run_test(|| {
-                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), 42, 1));
-                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), 43, 1));
-                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), 44, 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(43), 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(44), 1));
 
                 step_block();
 
@@ -61,7 +69,7 @@ mod token_distribution {
         fn requires_root() {
             run_test(|| {
                 assert_noop!(
-                    FractalTokenDistribution::set_weight(Origin::signed(1), 42, 1),
+                    FractalTokenDistribution::set_weight(Origin::signed(1), Address(42), 1),
                     sp_runtime::traits::BadOrigin
                 );
             });
@@ -70,7 +78,7 @@ mod token_distribution {
         #[test]
         fn distributes_additional_based_on_already_distributed() {
             run_test(|| {
-                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), 42, 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
                 step_block();
                 let first = Balances::free_balance(&42);
 
@@ -88,7 +96,7 @@ mod token_distribution {
                     Balances::total_issuance()
                 ));
 
-                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), 42, 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
                 step_block();
 
                 assert_eq!(Balances::free_balance(&42), FIRST_MINTING_TOTAL);
@@ -109,7 +117,7 @@ mod token_distribution {
             run_test(|| {
                 assert_ok!(FractalTokenDistribution::mint(Origin::root(), 43, 120000));
 
-                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), 42, 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
                 step_block();
 
                 assert_eq!(Balances::free_balance(&42), FIRST_MINTING_TOTAL);
@@ -125,7 +133,7 @@ mod token_distribution {
                     120_000
                 ));
 
-                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), 42, 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
                 step_block();
 
                 assert_eq!(Balances::free_balance(&42), FIRST_MINTING_TOTAL + 20_000);
@@ -141,7 +149,7 @@ mod token_distribution {
                     20_000
                 ));
 
-                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), 42, 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
                 step_block();
 
                 assert_eq!(Balances::free_balance(&42), 0);
@@ -181,15 +189,143 @@ mod token_distribution {
                     let _ = Balances::deposit_creating(&42, 10_001);
                     let _ = Balances::reserve(&42, 1);
 
-                    assert_ok!(FractalTokenDistribution::burn(
-                        Origin::signed(42),
-                        Some(10_001)
-                    ),);
+                    assert_ok!(FractalTokenDistribution::burn(Origin::signed(42), None));
 
                     assert_eq!(Balances::free_balance(&42), 0);
                     assert_eq!(Balances::reserved_balance(&42), 1);
                 });
             }
+
+            #[test]
+            fn rejects_more_than_free_balance() {
+                run_test(|| {
+                    let _ = Balances::deposit_creating(&42, 10_001);
+                    let _ = Balances::reserve(&42, 1);
+
+                    assert_noop!(
+                        FractalTokenDistribution::burn(Origin::signed(42), Some(10_001)),
+                        crate::Error::<Test>::InsufficientBalance
+                    );
+                });
+            }
+
+            #[test]
+            fn rejects_burning_nothing() {
+                run_test(|| {
+                    assert_noop!(
+                        FractalTokenDistribution::burn(Origin::signed(42), None),
+                        crate::Error::<Test>::NothingToBurn
+                    );
+                });
+            }
+        }
+
+        #[cfg(test)]
+        mod burn_to {
+            use super::*;
+            use crate::{BurnRecord, BurnTarget};
+
+            const ETHEREUM: u64 = 1;
+
+            fn address() -> BurnTarget {
+                BurnTarget::Address(vec![0xab; 20])
+            }
+
+            #[test]
+            fn records_burns_by_nonce() {
+                run_test(|| {
+                    let _ = Balances::deposit_creating(&42, 10_000);
+                    assert_ok!(FractalTokenDistribution::burn_to(
+                        Origin::signed(42),
+                        Some(1_000),
+                        ETHEREUM,
+                        address()
+                    ));
+                    assert_ok!(FractalTokenDistribution::burn_to(
+                        Origin::signed(42),
+                        None,
+                        ETHEREUM,
+                        BurnTarget::SwapId(b"swap".to_vec())
+                    ));
+
+                    assert_eq!(Balances::free_balance(&42), 0);
+                    assert_eq!(
+                        crate::Burns::<Test>::get(0),
+                        Some(BurnRecord {
+                            from: 42,
+                            amount: 1_000,
+                            chain: ETHEREUM,
+                            target: address(),
+                            block: 1,
+                        })
+                    );
+                    assert_eq!(
+                        crate::Burns::<Test>::get(1).map(|b| (b.amount, b.target)),
+                        Some((9_000, BurnTarget::SwapId(b"swap".to_vec())))
+                    );
+                    assert_eq!(crate::NextBurnNonce::<Test>::get(), 2);
+                });
+            }
+
+            #[test]
+            fn emits_burned_to() {
+                run_test(|| {
+                    let _ = Balances::deposit_creating(&42, 10_000);
+                    assert_ok!(FractalTokenDistribution::burn_to(
+                        Origin::signed(42),
+                        Some(1_000),
+                        ETHEREUM,
+                        address()
+                    ));
+
+                    assert_eq!(
+                        distribution_events().last(),
+                        Some(&crate::Event::BurnedTo {
+                            nonce: 0,
+                            from: 42,
+                            amount: 1_000,
+                            chain: ETHEREUM,
+                            target: address(),
+                            artificially_issued: 0,
+                            artificially_burned: 1_000,
+                        })
+                    );
+                });
+            }
+
+            #[test]
+            fn rejects_long_targets() {
+                run_test(|| {
+                    let _ = Balances::deposit_creating(&42, 10_000);
+
+                    assert_noop!(
+                        FractalTokenDistribution::burn_to(
+                            Origin::signed(42),
+                            None,
+                            ETHEREUM,
+                            BurnTarget::Address(vec![0; 65])
+                        ),
+                        crate::Error::<Test>::BurnTargetTooLong
+                    );
+                });
+            }
+
+            #[test]
+            fn rejects_more_than_free_balance() {
+                run_test(|| {
+                    let _ = Balances::deposit_creating(&42, 10_000);
+
+                    assert_noop!(
+                        FractalTokenDistribution::burn_to(
+                            Origin::signed(42),
+                            Some(10_001),
+                            ETHEREUM,
+                            address()
+                        ),
+                        crate::Error::<Test>::InsufficientBalance
+                    );
+                });
+            }
         }
 
         #[test]
@@ -202,7 +338,7 @@ mod token_distribution {
                 ));
                 assert_ok!(FractalTokenDistribution::burn(Origin::signed(43), None,));
 
-                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), 42, 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
                 step_block();
 
                 assert_eq!(Balances::free_balance(&42), FIRST_MINTING_TOTAL);
@@ -212,15 +348,31 @@ mod token_distribution {
         #[cfg(test)]
         mod mint {
             use super::*;
+            use crate::MintQuota;
+
+            const PERIOD: u64 = 10;
+
+            fn allow_minting(address: u64, total_cap: u64, period_allowance: u64) {
+                assert_ok!(FractalTokenDistribution::set_allow_minting(
+                    Origin::root(),
+                    address,
+                    true
+                ));
+                assert_ok!(FractalTokenDistribution::set_mint_quota(
+                    Origin::root(),
+                    address,
+                    MintQuota {
+                        total_cap,
+                        period_allowance,
+                        period: PERIOD,
+                    }
+                ));
+            }
 
             #[test]
             fn allows_minting_from_whitelisted_addresses() {
                 run_test(|| {
-                    assert_ok!(FractalTokenDistribution::set_allow_minting(
-                        Origin::root(),
-                        42,
-                        true
-                    ));
+                    allow_minting(42, 1_000_000, 1_000_000);
                     assert_ok!(FractalTokenDistribution::mint(
                         Origin::signed(42),
                         42,
@@ -259,6 +411,408 @@ mod token_distribution {
                     );
                 });
             }
+
+            #[test]
+            fn migration_keeps_existing_minters_minting() {
+                run_test(|| {
+                    assert_ok!(FractalTokenDistribution::set_allow_minting(
+                        Origin::root(),
+                        42,
+                        true
+                    ));
+                    allow_minting(43, 1_000, 1_000);
+                    crate::pallet::StorageVersion::<Test>::put(crate::Releases::V2);
+
+                    crate::migrations::migrate::<Test>();
+
+                    assert_ok!(FractalTokenDistribution::mint(
+                        Origin::signed(42),
+                        42,
+                        123456
+                    ));
+                    assert_eq!(
+                        crate::MintQuotas::<Test>::get(43).map(|q| q.total_cap),
+                        Some(1_000)
+                    );
+                    assert_eq!(
+                        crate::pallet::StorageVersion::<Test>::get(),
+                        crate::Releases::V3
+                    );
+                });
+            }
+
+            #[test]
+            fn disallows_minting_without_quota() {
+                run_test(|| {
+                    assert_ok!(FractalTokenDistribution::set_allow_minting(
+                        Origin::root(),
+                        42,
+                        true
+                    ));
+
+                    assert_noop!(
+                        FractalTokenDistribution::mint(Origin::signed(42), 42, 1),
+                        crate::Error::<Test>::NoMintQuota
+                    );
+                    assert_eq!(FractalTokenDistribution::mint_allowance(&42), 0);
+                });
+            }
+
+            #[test]
+            fn disallows_minting_past_total_cap() {
+                run_test(|| {
+                    allow_minting(42, 1_000, 1_000);
+                    assert_ok!(FractalTokenDistribution::mint(Origin::signed(42), 42, 600));
+
+                    for _ in 0..PERIOD {
+                        step_block();
+                    }
+
+                    assert_noop!(
+                        FractalTokenDistribution::mint(Origin::signed(42), 42, 401),
+                        crate::Error::<Test>::MintTotalCapExceeded
+                    );
+                    assert_ok!(FractalTokenDistribution::mint(Origin::signed(42), 42, 400));
+                });
+            }
+
+            #[test]
+            fn disallows_minting_past_period_allowance() {
+                run_test(|| {
+                    allow_minting(42, 1_000_000, 1_000);
+                    assert_ok!(FractalTokenDistribution::mint(Origin::signed(42), 42, 600));
+
+                    assert_noop!(
+                        FractalTokenDistribution::mint(Origin::signed(42), 42, 401),
+                        crate::Error::<Test>::MintPeriodAllowanceExceeded
+                    );
+                    assert_eq!(FractalTokenDistribution::mint_allowance(&42), 400);
+                });
+            }
+
+            #[test]
+            fn allowance_is_paid_back_each_block() {
+                run_test(|| {
+                    allow_minting(42, 1_000_000, 1_000);
+                    assert_ok!(FractalTokenDistribution::mint(Origin::signed(42), 42, 1_000));
+                    step_block();
+
+                    // A tenth of the allowance is paid back in each of the `PERIOD` blocks.
+                    assert_eq!(FractalTokenDistribution::mint_allowance(&42), 100);
+                    assert_noop!(
+                        FractalTokenDistribution::mint(Origin::signed(42), 42, 101),
+                        crate::Error::<Test>::MintPeriodAllowanceExceeded
+                    );
+                    assert_ok!(FractalTokenDistribution::mint(Origin::signed(42), 42, 100));
+                });
+            }
+
+            #[test]
+            fn allowance_does_not_reset_at_period_boundaries() {
+                run_test(|| {
+                    allow_minting(42, 1_000_000, 1_000);
+                    assert_ok!(FractalTokenDistribution::mint(Origin::signed(42), 42, 1));
+                    for _ in 1..PERIOD {
+                        step_block();
+                    }
+                    assert_ok!(FractalTokenDistribution::mint(Origin::signed(42), 42, 999));
+
+                    // A fixed window starting at the first mint would end here.
+                    step_block();
+
+                    assert_eq!(FractalTokenDistribution::mint_allowance(&42), 101);
+                    assert_noop!(
+                        FractalTokenDistribution::mint(Origin::signed(42), 42, 1_000),
+                        crate::Error::<Test>::MintPeriodAllowanceExceeded
+                    );
+                });
+            }
+
+            #[test]
+            fn allowance_renews_each_period() {
+                run_test(|| {
+                    allow_minting(42, 1_000_000, 1_000);
+                    assert_ok!(FractalTokenDistribution::mint(Origin::signed(42), 42, 1_000));
+
+                    for _ in 0..PERIOD {
+                        step_block();
+                    }
+
+                    assert_eq!(FractalTokenDistribution::mint_allowance(&42), 1_000);
+                    assert_ok!(FractalTokenDistribution::mint(Origin::signed(42), 42, 1_000));
+                });
+            }
+
+            #[test]
+            fn root_is_not_limited() {
+                run_test(|| {
+                    assert_ok!(FractalTokenDistribution::mint(Origin::root(), 42, 123456));
+                });
+            }
+
+            #[test]
+            fn setting_quota_requires_root() {
+                run_test(|| {
+                    assert_noop!(
+                        FractalTokenDistribution::set_mint_quota(
+                            Origin::signed(42),
+                            42,
+                            Default::default()
+                        ),
+                        sp_runtime::traits::BadOrigin
+                    );
+                });
+            }
+        }
+
+        #[test]
+        fn genesis_allows_minting() {
+            genesis_test_ext(crate::GenesisConfig {
+                destination_weights: vec![],
+                allowed_to_mint: vec![(
+                    42,
+                    crate::MintQuota {
+                        total_cap: 1_000_000,
+                        period_allowance: 1_000_000,
+                        period: 10,
+                    },
+                )],
+            })
+            .execute_with(|| {
+                assert_ok!(FractalTokenDistribution::mint(Origin::signed(42), 42, 123456));
+                assert_eq!(Balances::free_balance(&42), 123456);
+            });
+        }
+    }
+
+    #[test]
+    fn distributes_to_genesis_weights() {
+        genesis_test_ext(crate::GenesisConfig {
+            destination_weights: vec![(Address(42), 2), (Address(43), 1)],
+            allowed_to_mint: vec![],
+        })
+        .execute_with(|| {
+            step_block();
+            step_block();
+
+            assert_eq!(Balances::free_balance(&42), FIRST_MINTING_TOTAL * 2 / 3);
+            assert_eq!(Balances::free_balance(&43), FIRST_MINTING_TOTAL / 3);
+        });
+    }
+
+    #[cfg(test)]
+    mod purposes {
+        use super::*;
+        use crate::{Destination, Purpose};
+        use codec::Encode;
+        use frame_support::{storage::migration::put_storage_value, Blake2_128Concat, StorageHasher};
+
+        #[test]
+        fn issues_to_purpose_accounts() {
+            run_test(|| {
+                assert_ok!(FractalTokenDistribution::set_weight(
+                    Origin::root(),
+                    Destination::Purpose(Purpose::DataCaptureRewards),
+                    1
+                ));
+                assert_ok!(FractalTokenDistribution::set_weight(
+                    Origin::root(),
+                    Destination::Purpose(Purpose::StakingRewards),
+                    1
+                ));
+                step_block();
+
+                assert_eq!(
+                    Balances::free_balance(&DataCaptureAccount::get()),
+                    FIRST_MINTING_TOTAL / 2
+                );
+                assert_eq!(Balances::free_balance(&StakingAccount::get()), FIRST_MINTING_TOTAL / 2);
+            });
+        }
+
+        /// Stores a weight as it was keyed before V2, by account.
+        fn put_v1_weight(address: u64, weight: u32) {
+            put_storage_value(
+                b"FractalTokenDistribution",
+                b"DestinationWeights",
+                &Blake2_128Concat::hash(&address.encode()),
+                weight,
+            );
+        }
+
+        #[test]
+        fn migrates_weights_to_destinations() {
+            run_test(|| {
+                put_v1_weight(42, 1);
+                put_v1_weight(StakingAccount::get(), 2);
+                crate::pallet::StorageVersion::<Test>::put(crate::Releases::V1);
+
+                crate::migrations::migrate::<Test>();
+
+                let mut weights = crate::DestinationWeights::<Test>::iter().collect::<Vec<_>>();
+                weights.sort_by_key(|(_, weight)| *weight);
+                assert_eq!(
+                    weights,
+                    vec![(Address(42), 1), (Destination::Purpose(Purpose::StakingRewards), 2)]
+                );
+                assert_eq!(
+                    crate::pallet::StorageVersion::<Test>::get(),
+                    crate::Releases::V3
+                );
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod events {
+        use super::*;
+        use crate::Event;
+
+        #[test]
+        fn issuance_emits_issued_per_destination() {
+            run_test(|| {
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(43), 2));
+                step_block();
+
+                let mut issued = distribution_events()
+                    .into_iter()
+                    .filter(|e| matches!(e, Event::Issued { .. }))
+                    .collect::<Vec<_>>();
+                issued.sort_by_key(|e| match e {
+                    Event::Issued { destination, .. } => *destination,
+                    _ => unreachable!(),
+                });
+
+                assert_eq!(
+                    issued,
+                    vec![
+                        Event::Issued {
+                            destination: 42,
+                            amount: FIRST_MINTING_TOTAL / 3,
+                        },
+                        Event::Issued {
+                            destination: 43,
+                            amount: FIRST_MINTING_TOTAL / 3 * 2,
+                        },
+                    ]
+                );
+            });
+        }
+
+        #[test]
+        fn issuance_skips_zero_amounts() {
+            run_test(|| {
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(43), 0));
+                step_block();
+
+                assert_eq!(
+                    distribution_events()
+                        .into_iter()
+                        .filter(|e| matches!(e, Event::Issued { .. }))
+                        .collect::<Vec<_>>(),
+                    vec![Event::Issued {
+                        destination: 42,
+                        amount: FIRST_MINTING_TOTAL,
+                    }]
+                );
+            });
+        }
+
+        #[test]
+        fn set_weight_emits_previous_weight() {
+            run_test(|| {
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 1));
+                assert_ok!(FractalTokenDistribution::set_weight(Origin::root(), Address(42), 3));
+
+                assert_eq!(
+                    distribution_events().last(),
+                    Some(&Event::WeightSet {
+                        destination: Address(42),
+                        weight: 3,
+                        previous: 1,
+                    })
+                );
+            });
+        }
+
+        #[test]
+        fn set_allow_minting_emits_allow_minting_set() {
+            run_test(|| {
+                assert_ok!(FractalTokenDistribution::set_allow_minting(
+                    Origin::root(),
+                    42,
+                    true
+                ));
+
+                assert_eq!(
+                    distribution_events().last(),
+                    Some(&Event::AllowMintingSet {
+                        address: 42,
+                        allowed: true,
+                    })
+                );
+            });
+        }
+
+        #[test]
+        fn mint_and_burn_emit_running_totals() {
+            run_test(|| {
+                assert_ok!(FractalTokenDistribution::mint(Origin::root(), 42, 10_000));
+                assert_eq!(
+                    distribution_events().last(),
+                    Some(&Event::Minted {
+                        to: 42,
+                        amount: 10_000,
+                        artificially_issued: 10_000,
+                        artificially_burned: 0,
+                    })
+                );
+
+                assert_ok!(FractalTokenDistribution::burn(Origin::signed(42), Some(4_000)));
+                assert_eq!(
+                    distribution_events().last(),
+                    Some(&Event::Burned {
+                        from: 42,
+                        amount: 4_000,
+                        artificially_issued: 6_000,
+                        artificially_burned: 0,
+                    })
+                );
+
+                assert_ok!(FractalTokenDistribution::burn(Origin::signed(42), None));
+                let _ = Balances::deposit_creating(&43, 1_000);
+                assert_ok!(FractalTokenDistribution::burn(Origin::signed(43), None));
+                assert_eq!(
+                    distribution_events().last(),
+                    Some(&Event::Burned {
+                        from: 43,
+                        amount: 1_000,
+                        artificially_issued: 0,
+                        artificially_burned: 1_000,
+                    })
+                );
+            });
+        }
+
+        #[test]
+        fn increment_artificially_issued_emits_totals() {
+            run_test(|| {
+                assert_ok!(FractalTokenDistribution::increment_artificially_issued(
+                    Origin::root(),
+                    5_000
+                ));
+
+                assert_eq!(
+                    distribution_events().last(),
+                    Some(&Event::ArtificiallyIssuedIncremented {
+                        amount: 5_000,
+                        artificially_issued: 5_000,
+                        artificially_burned: 0,
+                    })
+                );
+            });
         }
     }
 }
@@ -12,7 +12,7 @@ use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
 use sp_runtime::traits::{
-    AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, Verify,
+    AccountIdLookup, BlakeTwo256, Block as BlockT, Convert, IdentifyAccount, NumberFor, Verify,
 };
 use sp_runtime::{
     create_runtime_str, generic, impl_opaque_keys,
@@ -26,6 +26,7 @@ use sp_version::RuntimeVersion;
 
 // A few exports that help ease life for downstream crates.
 pub use fractal_data_capture::Call as FractalMintingCall;
+pub use fractal_token_distribution::{Destination, MintQuota, Purpose};
 pub use frame_support::{
     construct_runtime, parameter_types,
     traits::{KeyOwnerProofSystem, Randomness},
@@ -103,10 +104,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     //   `spec_version`, and `authoring_version` are the same between Wasm and native.
     // This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
     //   the compatible custom types.
-    spec_version: 105,
+    spec_version: 124,
     impl_version: 1,
     apis: RUNTIME_API_VERSIONS,
-    transaction_version: 1,
+    transaction_version: 5,
 };
 
 /// This determines the average expected block time that we are targeting.
@@ -277,10 +278,17 @@ impl pallet_sudo::Config for Runtime {
 parameter_types! {
     pub const MaxRewardPerUser: Balance = 16_400 * (UNIT_BALANCE / 1000);
     pub const MintEveryNBlocks: BlockNumber = DAYS;
+    pub const MaxPayoutsPerBlock: u32 = 500;
+    pub const MaxGrowthWeightPerUser: u32 = 32;
+    pub const ChallengeResponseBlocks: BlockNumber = DAYS;
+    pub const MaxChallengesPerBlock: u32 = 100;
+    pub const MaxAccountsPerIdentity: u32 = 16;
+    pub const MaxExpiriesPerBlock: u32 = 100;
 
     pub const TotalIssuance: Balance = 400_000_000 * UNIT_BALANCE;
     pub const IssuanceHalfLife: BlockNumber = 10 * YEARS;
     pub const IssuanceCompleteAt: BlockNumber = 120 * YEARS;
+    pub const MaxBurnTargetLength: u32 = 64;
 
     // 5FCLDataCaptureRewardsxxxxxxxxxxxxxxxxxxxxxxxk7C
     pub const DataCaptureHoldingAccount: AccountId =
@@ -292,6 +300,16 @@ impl fractal_data_capture::Config for Runtime {
 
     type MaxRewardPerUser = MaxRewardPerUser;
     type MintEveryNBlocks = MintEveryNBlocks;
+    type MaxPayoutsPerBlock = MaxPayoutsPerBlock;
+    type MaxGrowthWeightPerUser = MaxGrowthWeightPerUser;
+
+    type Randomness = RandomnessCollectiveFlip;
+    type ChallengeResponseBlocks = ChallengeResponseBlocks;
+    type MaxChallengesPerBlock = MaxChallengesPerBlock;
+
+    type Staking = FractalStaking;
+    type MaxAccountsPerIdentity = MaxAccountsPerIdentity;
+    type MaxExpiriesPerBlock = MaxExpiriesPerBlock;
 
     type HoldingAccount = DataCaptureHoldingAccount;
 }
@@ -302,6 +320,8 @@ parameter_types! {
     // 5FCLStakingDistributionSourcexxxxxxxxxxxxxxxxwX3
     pub const DistributionSource: AccountId =
         AccountId::new(hex_literal::hex!("8a8697584b3c01d56761b4f0cad6a17ab72df5805ee45716d40e222b7d4a5737"));
+    pub const MaxMaturingPerBlock: u32 = 256;
+    pub const MaxStakesPerAccount: u32 = 32;
 }
 
 impl fractal_staking::Config for Runtime {
@@ -311,6 +331,11 @@ impl fractal_staking::Config for Runtime {
 
     type DistributeEveryNBlocks = DistributeEveryNBlocks;
     type DistributionSource = DistributionSource;
+    type EarlyUnstakePenaltyHandler = fractal_staking::Redistribute<Runtime>;
+    type Verification = FractalMinting;
+    type MaxMaturingPerBlock = MaxMaturingPerBlock;
+    type StakeLocks = FractalMinting;
+    type MaxStakesPerAccount = MaxStakesPerAccount;
 }
 
 impl fractal_token_distribution::Config for Runtime {
@@ -321,6 +346,20 @@ impl fractal_token_distribution::Config for Runtime {
     type TotalIssuance = TotalIssuance;
     type IssuanceHalfLife = IssuanceHalfLife;
     type IssuanceCompleteAt = IssuanceCompleteAt;
+    type MaxBurnTargetLength = MaxBurnTargetLength;
+    type PurposeAccounts = IssuancePurposes;
+}
+
+/// Pays issuance for each purpose to the account of the pallet that spends it.
+pub struct IssuancePurposes;
+
+impl Convert<Purpose, AccountId> for IssuancePurposes {
+    fn convert(purpose: Purpose) -> AccountId {
+        match purpose {
+            Purpose::DataCaptureRewards => DataCaptureHoldingAccount::get(),
+            Purpose::StakingRewards => DistributionSource::get(),
+        }
+    }
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -347,8 +386,8 @@ construct_runtime!(
         // This is still named FractalMinting for backwards compatibility with the Chrome
         // Extension.
         FractalMinting: fractal_data_capture::{Pallet, Call, Storage, Config<T>, Event<T>} = 8,
-        FractalTokenDistribution: fractal_token_distribution::{Pallet, Call, Storage, Event<T>} = 10,
-        FractalStaking: fractal_staking::{Pallet, Call, Storage, Event<T>} = 11,
+        FractalTokenDistribution: fractal_token_distribution::{Pallet, Call, Storage, Config<T>, Event<T>} = 10,
+        FractalStaking: fractal_staking::{Pallet, Call, Storage, Config<T>, Event<T>} = 11,
     }
 );
 
@@ -371,6 +410,7 @@ pub type SignedExtra = (
     frame_system::CheckNonce<Runtime>,
     frame_system::CheckWeight<Runtime>,
     pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+    fractal_data_capture::CheckDataCapture<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
@@ -500,6 +540,28 @@ impl_runtime_apis! {
         }
     }
 
+    impl fractal_data_capture_rpc_runtime_api::DataCaptureApi<Block, AccountId> for Runtime {
+        fn identity_statuses(
+            account: AccountId,
+        ) -> Vec<fractal_data_capture_rpc_runtime_api::IdentityStatus> {
+            FractalMinting::identity_statuses(&account)
+        }
+    }
+
+    impl fractal_staking_rpc_runtime_api::StakingApi<Block, AccountId, BlockNumber, Balance>
+        for Runtime
+    {
+        fn staking_summary(
+            account: AccountId,
+        ) -> fractal_staking_rpc_runtime_api::StakingSummary<BlockNumber, Balance> {
+            FractalStaking::staking_summary(&account)
+        }
+
+        fn lock_period_tiers() -> Vec<(BlockNumber, u32)> {
+            FractalStaking::lock_period_tiers()
+        }
+    }
+
     impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<Block, Balance> for Runtime {
         fn query_info(
             uxt: <Block as BlockT>::Extrinsic,
@@ -68,6 +68,27 @@ impl<D: Digest> MerkleTree<D> {
         }
     }
 
+    /// Rebuilds the right edge of a tree from its `frontier`. The result has the same hash as the
+    /// original, and is extended by exactly the same trees.
+    pub fn from_frontier<H: AsRef<[u8]>>(frontier: &[H]) -> Option<Self> {
+        let leaf = |hash: &H| {
+            let hash = hash.as_ref();
+            if hash.len() == D::output_size() {
+                Some(Self::leaf(GenericArray::clone_from_slice(hash)))
+            } else {
+                None
+            }
+        };
+
+        let mut hashes = frontier.iter().rev();
+        let mut tree = leaf(hashes.next()?)?;
+        for hash in hashes {
+            tree = Self::merge(leaf(hash)?, tree);
+        }
+
+        Some(tree)
+    }
+
     pub fn merge(l: Self, r: Self) -> Self {
         let mut hasher = D::new();
         hasher.update(&l.hash);
@@ -102,6 +123,112 @@ impl<D: Digest> MerkleTree<D> {
             .unwrap_or(1)
     }
 
+    /// Number of leaves visible in this tree, counting each pruned subtree as a single leaf.
+    pub fn leaf_count(&self) -> usize {
+        self.leaves().count()
+    }
+
+    /// Hashes of the left children along the right edge of the tree, followed by the hash of its
+    /// rightmost node. This is all `extends` looks at in the tree being extended.
+    pub fn frontier(&self) -> Vec<&[u8]> {
+        let mut result = Vec::new();
+
+        let mut node = self;
+        while let Some((l, r)) = node.children() {
+            result.push(l.hash.as_slice());
+            node = r;
+        }
+        result.push(node.hash.as_slice());
+
+        result
+    }
+
+    /// If this is the tree `push` builds by appending items to a tree of `base_count` items whose
+    /// balanced subtrees hash to `base_peaks`, largest first, the number of items appended and the
+    /// hashes of the balanced subtrees of the result. Like the `frontier`, these are all `extends`
+    /// looks at in the tree.
+    ///
+    /// Every leaf beyond the base is taken as a single item, so a pruned subtree of appended items
+    /// only counts once. The base's subtrees may be pruned or revealed.
+    pub fn appended_to<H: AsRef<[u8]>>(
+        &self,
+        base_count: usize,
+        base_peaks: &[H],
+    ) -> Option<(usize, Vec<&[u8]>)> {
+        if base_peaks.len() != base_count.count_ones() as usize {
+            return None;
+        }
+
+        let mut unmatched = base_peaks;
+        let appended = self.leaves_beyond(&mut unmatched)?;
+        let count = base_count + appended;
+        if !unmatched.is_empty() || !self.fits(0, count, base_count, base_peaks) {
+            return None;
+        }
+
+        let mut peaks = Vec::new();
+        let (mut node, mut remaining) = (self, count);
+        while !remaining.is_power_of_two() {
+            let (l, r) = node.children()?;
+            peaks.push(l.hash.as_slice());
+            remaining -= remaining.next_power_of_two() / 2;
+            node = r;
+        }
+        peaks.push(node.hash.as_slice());
+
+        Some((appended, peaks))
+    }
+
+    /// Number of leaves after the subtrees hashing to `peaks`, which are matched left to right
+    /// and removed from it.
+    fn leaves_beyond<H: AsRef<[u8]>>(&self, peaks: &mut &[H]) -> Option<usize> {
+        match (peaks.split_first(), self.children()) {
+            (Some((peak, rest)), _) if self.hash.as_slice() == peak.as_ref() => {
+                *peaks = rest;
+                Some(0)
+            }
+            // A pruned node that isn't a peak hides the rest of the base.
+            (Some(_), None) => None,
+            (None, None) => Some(1),
+            (_, Some((l, r))) => Some(l.leaves_beyond(peaks)? + r.leaves_beyond(peaks)?),
+        }
+    }
+
+    /// Whether this is the subtree `push` builds for items `start..start + count`, with the first
+    /// `base_count` items given by `base_peaks` and the rest by single leaves.
+    fn fits<H: AsRef<[u8]>>(
+        &self,
+        start: usize,
+        count: usize,
+        base_count: usize,
+        base_peaks: &[H],
+    ) -> bool {
+        if start + count <= base_count && count.is_power_of_two() {
+            // Balanced subtrees within the base are reached at its peaks, which hold its set bits
+            // in order.
+            let larger_peaks = (base_count >> count.trailing_zeros() >> 1).count_ones();
+            let peak = base_peaks.get(larger_peaks as usize);
+            return base_count & count != 0
+                && start == base_count & !(2 * count - 1)
+                && peak.map_or(false, |peak| self.hash.as_slice() == peak.as_ref());
+        }
+
+        match self.children() {
+            None => start >= base_count && count == 1,
+            Some(_) if count < 2 => false,
+            Some((l, r)) => {
+                let left_count = count.next_power_of_two() / 2;
+                l.fits(start, left_count, base_count, base_peaks)
+                    && r.fits(
+                        start + left_count,
+                        count - left_count,
+                        base_count,
+                        base_peaks,
+                    )
+            }
+        }
+    }
+
     pub fn balanced(&self) -> bool {
         match &self.children {
             None => true,
@@ -551,6 +678,127 @@ mod tests {
         }
     }
 
+    #[cfg(test)]
+    mod leaf_count {
+        use super::*;
+
+        #[quickcheck]
+        fn counts_all_items(items: Vec<String>) -> TestResult {
+            if items.len() == 0 {
+                return TestResult::discard();
+            }
+
+            let count = items.len();
+            let tree = MerkleTree::<Blake2b>::from_iter(items).unwrap();
+            TestResult::from_bool(tree.leaf_count() == count)
+        }
+
+        #[test]
+        fn pruned_subtrees_count_once() {
+            let tree = MerkleTree::<Blake2b>::from_iter(&["", "", "", "", "", "", ""]).unwrap();
+
+            assert_eq!(tree.prune_balanced().leaf_count(), 3);
+        }
+    }
+
+    #[cfg(test)]
+    mod appended_to {
+        use super::*;
+
+        fn tree(count: usize) -> MerkleTree<Blake2b> {
+            MerkleTree::from_iter((0..count).map(|i| i.to_string())).unwrap()
+        }
+
+        fn peaks(count: usize) -> Vec<Vec<u8>> {
+            let tree = tree(count);
+            let (_, peaks) = tree.appended_to::<&[u8]>(0, &[]).unwrap();
+            peaks.into_iter().map(|peak| peak.to_vec()).collect()
+        }
+
+        fn appended(tree: &MerkleTree<Blake2b>, base: usize) -> Option<usize> {
+            tree.appended_to(base, &peaks(base))
+                .map(|(appended, _)| appended)
+        }
+
+        #[quickcheck]
+        fn counts_every_item_of_new_tree(items: Vec<String>) -> TestResult {
+            if items.len() == 0 {
+                return TestResult::discard();
+            }
+
+            let count = items.len();
+            let tree = MerkleTree::<Blake2b>::from_iter(items).unwrap();
+            let appended = tree
+                .appended_to::<&[u8]>(0, &[])
+                .map(|(appended, _)| appended);
+            TestResult::from_bool(appended == Some(count))
+        }
+
+        #[quickcheck]
+        fn counts_items_appended_to_base(base: u8, added: u8) -> TestResult {
+            let (base, added) = (base as usize, added as usize);
+            if base == 0 {
+                return TestResult::discard();
+            }
+
+            TestResult::from_bool(appended(&tree(base + added), base) == Some(added))
+        }
+
+        #[test]
+        fn returns_peaks_of_result() {
+            let result = tree(7).appended_to(4, &peaks(4)).map(|(_, result)| {
+                result
+                    .into_iter()
+                    .map(|peak| peak.to_vec())
+                    .collect::<Vec<_>>()
+            });
+
+            assert_eq!(result, Some(peaks(7)));
+        }
+
+        #[test]
+        fn each_appended_leaf_counts_once() {
+            let pruned = MerkleTree::merge(tree(4), tree(4).prune_balanced());
+            let single = MerkleTree::merge(tree(4), MerkleTree::leaf_bytes("x"));
+
+            assert_eq!(appended(&pruned, 4), Some(1));
+            assert_eq!(appended(&single, 4), Some(1));
+        }
+
+        #[test]
+        fn base_may_be_pruned_or_revealed() {
+            let proof = tree(5).extension_proof(&tree(3)).unwrap();
+
+            assert_eq!(appended(&proof, 3), Some(2));
+            assert_eq!(appended(&tree(5), 3), Some(2));
+        }
+
+        #[test]
+        fn measures_pruned_trees_as_submitted() {
+            let submitted = tree(3).prune_balanced();
+            let (count, peaks) = submitted.appended_to::<&[u8]>(0, &[]).unwrap();
+            let pushed = submitted.clone().push("3");
+
+            assert_eq!(count, 2);
+            assert_eq!(
+                pushed
+                    .appended_to(count, &peaks)
+                    .map(|(appended, _)| appended),
+                Some(1)
+            );
+        }
+
+        #[test]
+        fn rejects_trees_hiding_the_base() {
+            let other = MerkleTree::<Blake2b>::from_iter(&["a", "b", "c"]).unwrap();
+            let (_, other_peaks) = other.appended_to::<&[u8]>(0, &[]).unwrap();
+
+            assert_eq!(tree(5).appended_to(3, &other_peaks), None);
+            assert_eq!(appended(&tree(5).prune_balanced(), 3), None);
+            assert_eq!(appended(&tree(2), 3), None);
+        }
+    }
+
     #[cfg(test)]
     mod push {
         use super::*;
@@ -632,6 +880,96 @@ mod tests {
         }
     }
 
+    #[cfg(test)]
+    mod frontier {
+        use super::*;
+
+        fn rebuilt(tree: &MerkleTree<Blake2b>) -> MerkleTree<Blake2b> {
+            MerkleTree::from_frontier(&tree.frontier()).unwrap()
+        }
+
+        #[test]
+        fn single_leaf() {
+            let tree = MerkleTree::<Blake2b>::from_iter(&["hello world"]).unwrap();
+
+            assert_eq!(tree.frontier(), vec![&hello_world_hash()[..]]);
+            assert!(rebuilt(&tree).deep_eq(&tree));
+        }
+
+        #[test]
+        fn rejects_wrong_hash_size() {
+            assert_eq!(MerkleTree::<Blake2b>::from_frontier(&[[0u8; 32]]), None);
+            assert_eq!(MerkleTree::<Blake2b>::from_frontier::<&[u8]>(&[]), None);
+        }
+
+        #[quickcheck]
+        fn same_hash(tree: MerkleTree<Blake2b>) -> TestResult {
+            TestResult::from_bool(rebuilt(&tree) == tree)
+        }
+
+        #[quickcheck]
+        fn valid_extension(first: Vec<String>, second: Vec<String>) -> TestResult {
+            if first.len() == 0 {
+                return TestResult::discard();
+            }
+
+            let first_tree = MerkleTree::<Blake2b>::from_iter(first.clone()).unwrap();
+            let second_tree =
+                MerkleTree::<Blake2b>::from_iter(first.into_iter().chain(second)).unwrap();
+            let extension = second_tree.extension_proof(&first_tree).unwrap();
+
+            TestResult::from_bool(
+                second_tree.extends(&rebuilt(&first_tree))
+                    && extension.extends(&rebuilt(&first_tree.prune_balanced())),
+            )
+        }
+
+        #[quickcheck]
+        fn item_changed(mut items: Vec<String>, index: usize) -> TestResult {
+            if items.len() == 0 || index >= items.len() {
+                return TestResult::discard();
+            }
+
+            let initial_tree = MerkleTree::<Blake2b>::from_iter(items.clone()).unwrap();
+
+            items[index].push_str("something");
+            let mutated_tree = MerkleTree::<Blake2b>::from_iter(items).unwrap();
+
+            TestResult::from_bool(!mutated_tree.extends(&rebuilt(&initial_tree)))
+        }
+
+        #[quickcheck]
+        fn peaks_are_balanced_subtrees(items: Vec<String>) -> TestResult {
+            if items.len() == 0 {
+                return TestResult::discard();
+            }
+
+            let count = items.len();
+            let tree = MerkleTree::<Blake2b>::from_iter(items).unwrap();
+            let (_, peaks) = tree.appended_to::<&[u8]>(0, &[]).unwrap();
+
+            TestResult::from_bool(
+                peaks.len() == count.count_ones() as usize
+                    && MerkleTree::<Blake2b>::from_frontier(&peaks).unwrap() == tree,
+            )
+        }
+
+        #[quickcheck]
+        fn extension_extends_peaks(first: Vec<String>, second: Vec<String>) -> TestResult {
+            if first.len() == 0 {
+                return TestResult::discard();
+            }
+
+            let first_tree = MerkleTree::<Blake2b>::from_iter(first.clone()).unwrap();
+            let second_tree =
+                MerkleTree::<Blake2b>::from_iter(first.into_iter().chain(second)).unwrap();
+            let extension = second_tree.extension_proof(&first_tree).unwrap();
+            let (_, peaks) = first_tree.appended_to::<&[u8]>(0, &[]).unwrap();
+
+            TestResult::from_bool(extension.extends(&MerkleTree::from_frontier(&peaks).unwrap()))
+        }
+    }
+
     #[cfg(test)]
     mod extension_proof {
         use super::*;